@@ -0,0 +1,92 @@
+//! Measures how many nodes per second the search gets through.
+//!
+//! Run with `cargo bench --bench nodes_per_second`.
+
+use std::{hint::black_box, time::Instant};
+
+use quatre_con::{
+    board::board::Board,
+    game::Evaluate,
+    tree::{Algorithm, Tree},
+};
+
+const POSITIONS: &[(&str, &str)] = &[
+    ("empty", ""),
+    ("opening", "4435"),
+    ("middlegame", "4435532664"),
+];
+
+const EVALUATIONS: usize = 200_000;
+
+fn bench_evaluate(name: &str, moves: &str) {
+    let board = <Board>::from_moves(moves).unwrap();
+
+    let start = Instant::now();
+    for _ in 0..EVALUATIONS {
+        black_box(black_box(&board).evaluate(false));
+    }
+    let elapsed = start.elapsed();
+
+    let nps = EVALUATIONS as f64 / elapsed.as_secs_f64();
+
+    println!(
+        "{:<12} evaluate                          {:>10} calls in {:>9.2?} ({:>12.0} nodes/s)",
+        name, EVALUATIONS, elapsed, nps
+    );
+}
+
+fn bench_search(name: &str, moves: &str, depth: usize, alg: Algorithm, threads: usize) {
+    let mut board = <Board>::from_moves(moves).unwrap();
+
+    let alg_name = format!("{:?}x{}", alg, threads);
+
+    let tree = Tree::new(depth, alg, false, 0).with_threads(threads);
+
+    let start = Instant::now();
+    let best = tree.get_best_move(&mut board);
+    let elapsed = start.elapsed();
+
+    let nodes = tree.eval_call_count();
+    let nps = nodes as f64 / elapsed.as_secs_f64();
+
+    println!(
+        "{:<12} {:<13} depth {} -> column {}: {:>10} nodes in {:>9.2?} ({:>12.0} nodes/s)",
+        name, alg_name, depth, best.column, nodes, elapsed, nps
+    );
+}
+
+/// How many evaluations ordering alpha-beta's moves saves, with and without a
+/// transposition table.
+fn bench_move_ordering(name: &str, moves: &str, depth: usize) {
+    for tt_size in [0, 16 * 1024 * 1024] {
+        let nodes = |move_ordering: bool| {
+            let tree = Tree::new(depth, Algorithm::AlphaBeta, false, tt_size)
+                .with_move_ordering(move_ordering);
+            tree.get_best_move(&mut <Board>::from_moves(moves).unwrap());
+            tree.eval_call_count()
+        };
+
+        let (plain, ordered) = (nodes(false), nodes(true));
+        let saved = 100.0 * (1.0 - ordered as f64 / plain as f64);
+
+        println!(
+            "{:<12} ordering   depth {} tt {:>2}MB: {:>10} nodes, {:>10} unordered ({:>5.1}% saved)",
+            name,
+            depth,
+            tt_size / 1024 / 1024,
+            ordered,
+            plain,
+            saved
+        );
+    }
+}
+
+fn main() {
+    for (name, moves) in POSITIONS {
+        bench_evaluate(name, moves);
+        bench_search(name, moves, 6, Algorithm::MiniMax, 1);
+        bench_search(name, moves, 7, Algorithm::AlphaBeta, 1);
+        bench_search(name, moves, 7, Algorithm::AlphaBeta, 4);
+        bench_move_ordering(name, moves, 9);
+    }
+}
@@ -2,6 +2,7 @@ use std::{
     collections::HashSet,
     fmt::{Debug, Display},
     hash::{Hash, Hasher},
+    str::FromStr,
 };
 
 use thiserror::Error;
@@ -9,8 +10,77 @@ use thiserror::Error;
 use super::{board_move::BoardMove, piece::Piece, square::Square};
 use crate::game::{Evaluate, GameEvaluation, MoM, MovePiece};
 
-const HEIGHT: usize = 6;
-const WIDTH: usize = 7;
+/*
+ * The board is stored as bitboards laid out column by column, bottom to top,
+ * with one spare bit on top of every column. For the standard 7x6 board:
+ *
+ *  .  .  .  .  .  .  .
+ *  5 12 19 26 33 40 47
+ *  4 11 18 25 32 39 46
+ *  3 10 17 24 31 38 45
+ *  2  9 16 23 30 37 44
+ *  1  8 15 22 29 36 43
+ *  0  7 14 21 28 35 42
+ *
+ * The spare row is always empty so shifting a line of pieces past the edge of
+ * a column always lands on a zero instead of wrapping into the next column.
+ */
+type Bits = u128;
+
+const fn bottom_mask(width: usize, height: usize) -> Bits {
+    assert!(
+        width > 0 && height > 0,
+        "The board needs at least one square"
+    );
+    assert!(
+        width * (height + 1) <= Bits::BITS as usize,
+        "The board doesn't fit in a bitboard"
+    );
+
+    let mut mask = 0;
+    let mut column = 0;
+    while column < width {
+        mask |= 1 << (column * (height + 1));
+        column += 1;
+    }
+    mask
+}
+
+const fn min(a: usize, b: usize) -> usize {
+    if a < b {
+        a
+    } else {
+        b
+    }
+}
+
+/// The weight of every square, indexed by its bit.
+///
+/// A square's weight is the number of lines, in each direction, that start on
+/// the board and run through it.
+const fn weights(width: usize, height: usize, connect: usize) -> [u8; Bits::BITS as usize] {
+    assert!(connect > 1, "Unsupported connect length");
+
+    let mut weights = [0; Bits::BITS as usize];
+    let reach = connect - 1;
+
+    let mut column = 0;
+    while column < width {
+        let mut row = 0;
+        while row < height {
+            // How many starting squares below/left of us are on the board
+            let north = min(row, reach) + 1;
+            let east = min(column, reach) + 1;
+            let north_east = min(min(row, column), reach) + 1;
+            let north_west = min(min(row, width - 1 - column), reach) + 1;
+
+            weights[row + column * (height + 1)] = (north + east + north_east + north_west) as u8;
+            row += 1;
+        }
+        column += 1;
+    }
+    weights
+}
 
 #[derive(Error, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum BoardError {
@@ -20,99 +90,316 @@ pub enum BoardError {
     OutOfRange(usize),
     #[error("Move failed to provide a color when it was needed.")]
     NoColor,
+    #[error("'{0}' isn't a column.")]
+    NotAColumn(char),
+    #[error("Move {ply} (column {column}) of the sequence can't be played.")]
+    IllegalSequence { ply: usize, column: usize },
+    #[error("There isn't a full round of moves to take back.")]
+    NothingToUndo,
 }
 
-enum SquareResult {
-    Connect(Piece),
-    Disparate(i64, Vec<Threat>),
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Threat {
-    column: usize,
-    row: usize,
-    color: Piece,
+/// Why a picture of a board couldn't be read, rows counted from the bottom
+/// and columns from the left, both starting at 1.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ParseBoardError {
+    #[error("Expected {expected} rows but found {found}.")]
+    WrongRowCount { expected: usize, found: usize },
+    #[error("Row {row} should be {expected} squares wide but is {found}.")]
+    WrongRowWidth {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("'{square}' at row {row} column {column} isn't Y, R or _.")]
+    UnexpectedSquare {
+        row: usize,
+        column: usize,
+        square: char,
+    },
+    #[error("The piece at row {row} column {column} is floating.")]
+    FloatingPiece { row: usize, column: usize },
+    #[error("{yellow} yellow and {red} red pieces can't happen, yellow goes first.")]
+    ImpossibleCounts { yellow: usize, red: usize },
+    #[error("No sequence of moves reaches this board.")]
+    Unreachable,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
-pub struct Board {
-    board: [[Square; WIDTH]; HEIGHT],
-    threats: HashSet<Threat>,
+/// A `W` wide, `H` high board where `N` in a row wins.
+///
+/// Every size up to 128 bits of bitboard (`W * (H + 1)`) is supported, so
+/// 8x7 and 9x7 fit alongside the standard 7x6. The move notation of
+/// [`Board::from_moves`] has one digit per column though, so boards that use
+/// it can be at most 9 wide, which is checked at compile time.
+#[derive(Default, Debug, Clone, Eq)]
+pub struct Board<const W: usize = 7, const H: usize = 6, const N: usize = 4> {
+    yellow: Bits,
+    red: Bits,
+    threats: [Bits; 2],
     turn_count: usize,
     show_threats: bool,
+    /// The columns played to get here, oldest first.
+    history: Vec<usize>,
+}
+
+/// Two boards are the same position however they got there.
+impl<const W: usize, const H: usize, const N: usize> PartialEq for Board<W, H, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.yellow == other.yellow
+            && self.red == other.red
+            && self.threats == other.threats
+            && self.turn_count == other.turn_count
+            && self.show_threats == other.show_threats
+    }
 }
 
-impl Hash for Board {
-    fn hash<H>(&self, state: &mut H)
+impl<const W: usize, const H: usize, const N: usize> Hash for Board<W, H, N> {
+    fn hash<S>(&self, state: &mut S)
     where
-        H: Hasher,
+        S: Hasher,
     {
-        self.board.hash(state);
+        self.yellow.hash(state);
+        self.red.hash(state);
     }
 }
 
-impl AsRef<Board> for Board {
+impl<const W: usize, const H: usize, const N: usize> AsRef<Board<W, H, N>> for Board<W, H, N> {
     fn as_ref(&self) -> &Self {
         self
     }
 }
 
-impl Board {
-    pub fn new(show_threats: bool) -> Self {
-        let turn_count = 0;
-        let board = [[Square::Empty; WIDTH]; HEIGHT];
+impl<const W: usize, const H: usize, const N: usize> Board<W, H, N> {
+    pub const WIDTH: usize = W;
+    pub const HEIGHT: usize = H;
+    pub const CONNECT: usize = N;
+
+    const COLUMN_BITS: usize = H + 1;
+
+    /// Fails to compile boards too wide to number their columns 1 to 9.
+    const ONE_DIGIT_COLUMNS: () = assert!(W <= 9, "The move notation needs W <= 9");
 
+    /// Bit shifts for one step North, East, North East and South East.
+    const DIRECTIONS: [usize; 4] = [1, H + 1, H + 2, H];
+
+    const BOTTOM_MASK: Bits = bottom_mask(W, H);
+    const BOARD_MASK: Bits = Self::BOTTOM_MASK * ((1 << H) - 1);
+    const WEIGHTS: &'static [u8; Bits::BITS as usize] = &weights(W, H, N);
+
+    pub fn new(show_threats: bool) -> Self {
         Self {
-            board,
-            threats: HashSet::new(),
-            turn_count,
             show_threats,
+            ..Default::default()
         }
     }
+
+    /// Play out a sequence of columns, e.g. `4453`, from the empty board.
+    ///
+    /// Columns are numbered from 1 on the left, one digit per move, so only
+    /// boards up to 9 wide have a notation.
+    pub fn from_moves(moves: &str) -> Result<Self, BoardError> {
+        let mut board = Self::default();
+        board.play_moves(moves)?;
+        Ok(board)
+    }
+
+    /// Play out columns numbered from 0 from the empty board, for tests that
+    /// work with columns rather than the notation.
     #[cfg(test)]
-    fn from_str(board_str: &str) -> Self {
-        let rows: Vec<&str> = board_str.split('\n').collect();
+    pub(crate) fn from_columns(columns: &[usize]) -> Self {
+        let mut board = Self::default();
+        for &column in columns {
+            board.apply_move(&column.into()).unwrap();
+        }
+        board
+    }
+
+    /// Play out a sequence of columns on this board. Nothing is played if any
+    /// move of the sequence is illegal, including moves after the game is won.
+    pub fn play_moves(&mut self, moves: &str) -> Result<(), BoardError> {
+        let () = Self::ONE_DIGIT_COLUMNS;
+        let mut board = self.clone();
+
+        for (i, c) in moves.trim().chars().enumerate() {
+            let column = c
+                .to_digit(10)
+                .filter(|&d| d > 0)
+                .ok_or(BoardError::NotAColumn(c))? as usize;
+            let illegal = BoardError::IllegalSequence { ply: i + 1, column };
+
+            if Self::has_connect(board.yellow) || Self::has_connect(board.red) {
+                return Err(illegal);
+            }
+            board
+                .apply_move(&(column - 1).into())
+                .map_err(|_| illegal)?;
+        }
 
-        let mut turn_count = 0;
-        let mut board = [[Square::Empty; WIDTH]; HEIGHT];
+        *self = board;
+        Ok(())
+    }
 
-        for (i, row) in rows.iter().rev().map(|l| l.trim()).enumerate() {
-            let row_iter = row.chars().map(|c| match c {
-                'R' => {
-                    turn_count += 1;
-                    Square::NonEmpty(Piece::Red)
-                }
-                'Y' => {
-                    turn_count += 1;
-                    Square::NonEmpty(Piece::Yellow)
-                }
-                '_' => Square::Empty,
-                _ => unreachable!(),
+    /// The sequence of columns played to reach this board, in the notation
+    /// [`Board::from_moves`] reads.
+    pub fn to_moves(&self) -> String {
+        let () = Self::ONE_DIGIT_COLUMNS;
+        self.history
+            .iter()
+            .map(|&c| char::from_digit(c as u32 + 1, 10).unwrap())
+            .collect()
+    }
+
+    /// The columns played to reach this board, oldest first.
+    pub fn history(&self) -> &[usize] {
+        &self.history
+    }
+
+    /// Read a picture of the board without checking it could happen in a
+    /// game, so tests can evaluate any arrangement of pieces.
+    fn parse_picture(board_str: &str) -> Result<Self, ParseBoardError> {
+        let mut board = Self::default();
+
+        // Rows copied from the board's display keep their frame and spaces,
+        // and its column numbers are on a line starting with `=`
+        let rows: Vec<String> = board_str
+            .split('\n')
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('='))
+            .map(|l| {
+                l.trim_matches('|')
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect()
+            })
+            .collect();
+
+        if rows.len() != H {
+            return Err(ParseBoardError::WrongRowCount {
+                expected: H,
+                found: rows.len(),
             });
+        }
+
+        for (i, row) in rows.iter().rev().enumerate() {
+            let found = row.chars().count();
+            if found != W {
+                return Err(ParseBoardError::WrongRowWidth {
+                    row: i + 1,
+                    expected: W,
+                    found,
+                });
+            }
 
-            for (j, c) in row_iter.enumerate() {
-                board[i - 1][j] = c;
+            for (j, c) in row.chars().enumerate() {
+                let bit = Self::square_bit(i, j);
+                match c {
+                    'R' => board.red |= bit,
+                    'Y' => board.yellow |= bit,
+                    '_' => continue,
+                    _ => {
+                        return Err(ParseBoardError::UnexpectedSquare {
+                            row: i + 1,
+                            column: j + 1,
+                            square: c,
+                        })
+                    }
+                }
+                board.turn_count += 1;
             }
         }
 
-        Self {
-            board,
-            threats: HashSet::new(),
-            turn_count,
-            show_threats: false,
+        Ok(board)
+    }
+
+    #[cfg(test)]
+    fn from_picture(board_str: &str) -> Self {
+        Self::parse_picture(board_str).unwrap()
+    }
+
+    /// Find a game that ends on this board by taking pieces back off the top,
+    /// last mover first. A won board must only have been won by its last move.
+    fn reconstruct_history(&self) -> Option<Vec<usize>> {
+        fn take_back<const W: usize, const H: usize, const N: usize>(
+            board: &mut Board<W, H, N>,
+            dead_ends: &mut HashSet<(Bits, Bits)>,
+            history: &mut Vec<usize>,
+        ) -> bool {
+            if board.is_empty() {
+                return true;
+            }
+            if dead_ends.contains(&(board.yellow, board.red)) {
+                return false;
+            }
+
+            let mask = board.mask();
+            for column in 0..W {
+                let pieces = mask & Board::<W, H, N>::column_mask(column);
+                if pieces == 0 {
+                    continue;
+                }
+                // The highest piece in the column
+                let bit = 1 << (Bits::BITS - 1 - pieces.leading_zeros());
+
+                // Whoever moved last has to own the piece
+                let last_mover = match board.whos_to_play() {
+                    Piece::Yellow => &mut board.red,
+                    Piece::Red => &mut board.yellow,
+                };
+                if *last_mover & bit == 0 {
+                    continue;
+                }
+                *last_mover &= !bit;
+                board.turn_count -= 1;
+
+                let over = Board::<W, H, N>::has_connect(board.yellow)
+                    || Board::<W, H, N>::has_connect(board.red);
+                if !over && take_back(board, dead_ends, history) {
+                    history.push(column);
+                    return true;
+                }
+
+                board.turn_count += 1;
+                match board.whos_to_play() {
+                    Piece::Yellow => board.red |= bit,
+                    Piece::Red => board.yellow |= bit,
+                }
+            }
+
+            dead_ends.insert((board.yellow, board.red));
+            false
         }
+
+        let mut board = self.clone();
+        let mut history = Vec::with_capacity(self.turn_count);
+
+        take_back(&mut board, &mut HashSet::new(), &mut history).then_some(history)
+    }
+
+    fn square_bit(row: usize, column: usize) -> Bits {
+        1 << (row + column * Self::COLUMN_BITS)
+    }
+
+    fn column_mask(column: usize) -> Bits {
+        ((1 << H) - 1) << (column * Self::COLUMN_BITS)
+    }
+
+    fn top_mask(column: usize) -> Bits {
+        Self::square_bit(H - 1, column)
+    }
+
+    fn mask(&self) -> Bits {
+        self.yellow | self.red
     }
 
-    #[allow(dead_code)]
     fn is_empty(&self) -> bool {
-        !self.board.iter().any(|r| r.iter().any(|c| !c.is_empty()))
+        self.mask() == 0
     }
 
     fn is_full(&self) -> bool {
-        !self.board.iter().any(|r| r.iter().any(|c| c.is_empty()))
+        self.mask() == Self::BOARD_MASK
     }
 
-    fn whos_to_play(&self) -> Piece {
+    /// Whose turn it is.
+    pub fn whos_to_play(&self) -> Piece {
         if self.turn_count & 1 == 0 {
             Piece::Yellow
         } else {
@@ -120,190 +407,106 @@ impl Board {
         }
     }
 
-    fn eval_square(&self, i: usize, j: usize) -> SquareResult {
-        let mut evals: [i64; 4] = match self.board[i][j] {
-            Square::NonEmpty(Piece::Yellow) => [1; 4],
-            Square::NonEmpty(Piece::Red) => [-1; 4],
-            Square::Empty => [0; 4],
-        };
-
-        let mut threats = [
-            Vec::with_capacity(3),
-            Vec::with_capacity(3),
-            Vec::with_capacity(3),
-            Vec::with_capacity(3),
-        ];
-
-        let mut blocked = [false; 4];
+    fn read_square(&self, row: usize, column: usize) -> Square {
+        let bit = Self::square_bit(row, column);
 
-        // Get the buisness done
-        self.sum_eval_square_mask(i, j, &mut evals, &mut blocked, &mut threats);
-
-        let mut ret_threats = vec![];
-        for (d, e) in evals.iter().enumerate() {
-            if blocked[d] {
-                continue;
-            }
-            let color = match e {
-                4 => return SquareResult::Connect(Piece::Yellow),
-                -4 => return SquareResult::Connect(Piece::Red),
-                3 => Piece::Yellow,
-                -3 => Piece::Red,
-                _ => continue,
-            };
-            let (i, j) = if threats[d].is_empty() {
-                (i, j)
-            } else {
-                threats[d][0]
-            };
-            ret_threats.push(Threat {
-                row: i,
-                column: j,
-                color,
-            })
+        if self.yellow & bit != 0 {
+            Square::NonEmpty(Piece::Yellow)
+        } else if self.red & bit != 0 {
+            Square::NonEmpty(Piece::Red)
+        } else {
+            Square::Empty
         }
+    }
 
-        SquareResult::Disparate(evals.into_iter().sum(), ret_threats)
-    }
-
-    fn sum_eval_square_mask(
-        &self,
-        i: usize,
-        j: usize,
-        evals: &mut [i64; 4],
-        blocked: &mut [bool; 4],
-        threats: &mut [Vec<(usize, usize)>; 4],
-    ) {
-        for k in 1..=3 {
-            /*
-             * *  *  *
-             *  * * *
-             *   ***
-             *    ****
-             */
-
-            let directions = [
-                // North West
-                (i + k, j.overflowing_sub(k).0),
-                // North
-                (i + k, j),
-                // North East
-                (i + k, j + k),
-                // East
-                (i, j + k),
-            ];
-
-            for (d, (i, j)) in directions.iter().enumerate() {
-                if !self.is_in_bounds(*i, *j) {
-                    continue;
-                }
-                let Some(square) = self.read_bounded(*i, *j) else {
-                    threats[d].push((*i, *j));
-                    continue;
-                };
-
-                let eval: i64 = match square {
-                    Piece::Yellow => 1,
-                    Piece::Red => -1,
-                };
+    /// Shift a bitboard along a line: up the bits for positive steps and down
+    /// for negative ones.
+    fn shift(pieces: Bits, steps: isize) -> Bits {
+        if steps >= 0 {
+            pieces << steps
+        } else {
+            pieces >> -steps
+        }
+    }
 
-                // Does the new eval match the sign of what we've seen up to now.
-                // Even if the sign switches the |= won't overright the blocking.
-                blocked[d] |= eval.signum() != evals[d].signum() && evals[d].signum() != 0;
+    fn has_connect(pieces: Bits) -> bool {
+        Self::DIRECTIONS.iter().any(|&d| {
+            // Keep only the pieces that start a line of `N`
+            let line = (1..N).fold(pieces, |line, k| line & (pieces >> (k * d)));
+            line != 0
+        })
+    }
 
-                evals[d] += eval;
+    /// Every empty square that would finish a line for `pieces`.
+    fn threat_mask(&self, pieces: Bits) -> Bits {
+        let mut threats = 0;
+
+        for d in Self::DIRECTIONS {
+            let d = d as isize;
+            // The hole can be any square of the line
+            for hole in 0..N as isize {
+                let line = (0..N as isize)
+                    .filter(|&k| k != hole)
+                    .fold(Self::BOARD_MASK, |line, k| {
+                        line & Self::shift(pieces, (hole - k) * d)
+                    });
+                threats |= line;
             }
         }
-    }
 
-    fn is_in_bounds(&self, i: usize, j: usize) -> bool {
-        i < HEIGHT && j < WIDTH
+        threats & Self::BOARD_MASK & !self.mask()
     }
 
-    fn read_bounded(&self, i: usize, j: usize) -> Option<Piece> {
-        if !self.is_in_bounds(i, j) {
-            return None;
+    fn weight(mut pieces: Bits) -> isize {
+        let mut weight = 0;
+        while pieces != 0 {
+            weight += Self::WEIGHTS[pieces.trailing_zeros() as usize] as isize;
+            pieces &= pieces - 1;
         }
+        weight
+    }
 
-        match self.board[i][j] {
-            Square::Empty => None,
-            Square::NonEmpty(color) => Some(color),
-        }
+    pub fn set_show_threats(&mut self, show_threats: bool) {
+        self.show_threats = show_threats;
     }
 
     pub fn clear_threats(&mut self) {
-        self.threats.clear();
+        self.threats = [0; 2];
     }
 
     pub fn calculate_threats(&mut self) {
-        for (i, row) in self.board.iter().enumerate() {
-            for (j, _) in row.iter().enumerate() {
-                let squares = self.eval_square(i, j);
-
-                if let SquareResult::Disparate(_, threats) = squares {
-                    for t in threats {
-                        self.threats.insert(t);
-                    }
-                }
-            }
-        }
+        self.threats = [self.threat_mask(self.yellow), self.threat_mask(self.red)];
     }
 
-    fn process_threats(&self, threat_board: [[Option<Threat>; HEIGHT]; WIDTH]) -> isize {
+    fn process_threats(&self, yellow_threats: Bits, red_threats: Bits) -> isize {
         let mut adjustment = 0;
-        for col in threat_board {
-            // The first threat that will be seen in the column
-            let mut first_threat = None;
-            // The same, but stacked
-            let mut stacked_threat = None;
+        for column in 0..W {
+            let column_mask = Self::column_mask(column);
+            let all = (yellow_threats | red_threats) & column_mask;
 
-            // Go throught all the pairs of indexes backwards
-            for w in (0..HEIGHT).collect::<Vec<usize>>().windows(2).rev() {
-                let [less, more] = w else {
-                    continue;
-                };
-                let Some(bot_threat) = &col[*less] else {
-                    continue;
-                };
-
-                first_threat = Some(bot_threat);
+            // The first threat that will be seen in the column
+            let first = all & all.wrapping_neg();
+            if first == 0 {
+                continue;
+            }
 
-                let Some(top_threat) = &col[*more] else {
+            for (threats, sign) in [(yellow_threats, 1), (red_threats, -1)] {
+                if threats & first == 0 {
                     continue;
-                };
-
-                if top_threat.color == bot_threat.color {
-                    stacked_threat = Some(bot_threat)
                 }
-            }
-            let first = match first_threat {
-                Some(t) => {
-                    let adj = match t.color {
-                        Piece::Yellow => 10,
-                        Piece::Red => -10,
-                    };
-                    adjustment += adj;
-                    t
+                adjustment += 10 * sign;
+
+                // The same, but stacked
+                if threats & (first << 1) & column_mask != 0 {
+                    adjustment += 30 * sign;
                 }
-                None => continue,
-            };
-            let Some(stacked) = stacked_threat else {
-                continue;
-            };
-            if stacked.row > first.row {
-                continue;
             }
-            let adj = match stacked.color {
-                Piece::Yellow => 30,
-                Piece::Red => -30,
-            };
-            adjustment += adj;
         }
         adjustment
     }
 }
 
-impl Evaluate for Board {
+impl<const W: usize, const H: usize, const N: usize> Evaluate for Board<W, H, N> {
     fn min_or_maxing(&self) -> MoM {
         match self.whos_to_play() {
             Piece::Red => MoM::Min,
@@ -311,93 +514,85 @@ impl Evaluate for Board {
         }
     }
     fn evaluate(&self, use_threats: bool) -> crate::game::GameEvaluation {
-        let mut eval = 0;
-        let mut threats_set = [[None; HEIGHT]; WIDTH];
-        for (i, row) in self.board.iter().enumerate() {
-            for (j, _) in row.iter().enumerate() {
-                let squares = self.eval_square(i, j);
-
-                match squares {
-                    SquareResult::Connect(Piece::Yellow) => return GameEvaluation::Win,
-                    SquareResult::Connect(Piece::Red) => return GameEvaluation::Lose,
-                    SquareResult::Disparate(val, threats) => {
-                        // zip threat
-                        for t in threats {
-                            let r = t.row;
-                            let c = t.column;
-                            threats_set[c][r] = Some(t);
-                        }
-                        eval += val as isize;
-                    }
-                }
-            }
+        if Self::has_connect(self.yellow) {
+            return GameEvaluation::Win(0);
+        }
+        if Self::has_connect(self.red) {
+            return GameEvaluation::Lose(0);
         }
 
         if self.is_full() {
             return GameEvaluation::Draw;
         }
 
+        let mut eval = Self::weight(self.yellow) - Self::weight(self.red);
+
         if use_threats {
-            let threat_adjustment = self.process_threats(threats_set);
+            let threat_adjustment =
+                self.process_threats(self.threat_mask(self.yellow), self.threat_mask(self.red));
             eval += threat_adjustment;
         }
 
-        // Process threats
         GameEvaluation::OnGoing(eval)
     }
+
+    fn is_over(&self) -> bool {
+        Self::has_connect(self.yellow) || Self::has_connect(self.red) || self.is_full()
+    }
 }
 
-impl MovePiece for Board {
+impl<const W: usize, const H: usize, const N: usize> MovePiece for Board<W, H, N> {
     type MoveData = BoardMove;
     type MoveError = BoardError;
 
     fn apply_move(&mut self, move_data: &Self::MoveData) -> Result<(), Self::MoveError> {
         let column = move_data.column;
 
-        let color = self.whos_to_play();
-
-        if column >= WIDTH {
+        if column >= W {
             return Err(BoardError::OutOfRange(column));
         }
 
-        let square: Option<&mut Square> = self
-            .board
-            .iter_mut()
-            .map(|r| &mut r[column])
-            .find(|c| c.is_empty());
-
-        match square {
-            None => Err(BoardError::InvalidMove(column)),
-            Some(s) => {
-                self.turn_count += 1;
-                *s = Square::NonEmpty(color);
-                Ok(())
-            }
+        let mask = self.mask();
+        if mask & Self::top_mask(column) != 0 {
+            return Err(BoardError::InvalidMove(column));
+        }
+
+        // Adding the bottom bit carries up to the first empty square
+        let bit = (mask + Self::square_bit(0, column)) & Self::column_mask(column);
+
+        match self.whos_to_play() {
+            Piece::Yellow => self.yellow |= bit,
+            Piece::Red => self.red |= bit,
         }
+        self.turn_count += 1;
+        self.history.push(column);
+
+        Ok(())
     }
 
     fn remove_move(&mut self, move_data: &Self::MoveData) -> Result<(), Self::MoveError> {
         let column = move_data.column;
 
-        if column >= WIDTH {
+        if column >= W {
             return Err(BoardError::OutOfRange(column));
         }
 
-        let square: Option<&mut Square> = self
-            .board
-            .iter_mut()
-            .rev()
-            .map(|r| &mut r[column])
-            .find(|c| !c.is_empty());
-
-        match square {
-            None => Err(BoardError::InvalidMove(column)),
-            Some(s) => {
-                self.turn_count -= 1;
-                *s = Square::Empty;
-                Ok(())
-            }
+        let pieces = self.mask() & Self::column_mask(column);
+        if pieces == 0 {
+            return Err(BoardError::InvalidMove(column));
         }
+
+        // The highest piece in the column
+        let bit = 1 << (Bits::BITS - 1 - pieces.leading_zeros());
+
+        self.yellow &= !bit;
+        self.red &= !bit;
+        self.turn_count -= 1;
+        if let Some(i) = self.history.iter().rposition(|&c| c == column) {
+            self.history.remove(i);
+        }
+
+        Ok(())
     }
 
     fn is_move_valid(&self, move_data: &Self::MoveData) -> bool {
@@ -409,45 +604,108 @@ impl MovePiece for Board {
 
         self.list_moves()
             .iter()
-            .map(|m| m.column)
-            .find(|i| move_data.column == *i)
-            .is_some()
+            .any(|m| m.column == move_data.column)
     }
 
     fn list_moves(&self) -> Vec<Self::MoveData> {
         let color = self.whos_to_play();
+        let mask = self.mask();
 
-        self.board[HEIGHT - 1]
-            .iter()
-            .enumerate()
-            .filter(|(_, s)| s.is_empty())
-            .map(|(i, _)| (i, color).into())
+        (0..W)
+            .filter(|&c| mask & Self::top_mask(c) == 0)
+            .map(|c| (c, color).into())
             .collect()
     }
+
+    /// Winning moves first, then moves that stop the other player winning,
+    /// then the centre columns as they take part in the most lines.
+    fn ordered_moves(&self) -> Vec<Self::MoveData> {
+        let (mine, theirs) = match self.whos_to_play() {
+            Piece::Yellow => (self.yellow, self.red),
+            Piece::Red => (self.red, self.yellow),
+        };
+
+        // The square each column's next piece lands on
+        let playable = (self.mask() + Self::BOTTOM_MASK) & Self::BOARD_MASK;
+        let wins = self.threat_mask(mine) & playable;
+        let blocks = self.threat_mask(theirs) & playable;
+
+        let mut moves = self.list_moves();
+        moves.sort_by_key(|m| {
+            let column = Self::column_mask(m.column);
+            (
+                wins & column == 0,
+                blocks & column == 0,
+                (2 * m.column).abs_diff(W - 1),
+            )
+        });
+        moves
+    }
 }
 
-impl Display for Board {
+/// Reads a picture of the board, one line per row from the top and `Y`, `R` or
+/// `_` per square. The frame and spacing of the board's display are allowed.
+///
+/// Only boards that a game could reach are accepted, and the board remembers a
+/// sequence of moves that reaches it.
+impl<const W: usize, const H: usize, const N: usize> FromStr for Board<W, H, N> {
+    type Err = ParseBoardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut board = Self::parse_picture(s)?;
+
+        for column in 0..W {
+            let pieces = board.mask() & Self::column_mask(column);
+            // Adding the bottom bit carries up to the first gap, any piece
+            // still above it is floating
+            let carried = pieces + Self::square_bit(0, column);
+            let gap = carried & carried.wrapping_neg();
+            let floating = pieces & !(gap | (gap - 1));
+            if floating != 0 {
+                let row = floating.trailing_zeros() as usize - column * Self::COLUMN_BITS;
+                return Err(ParseBoardError::FloatingPiece {
+                    row: row + 1,
+                    column: column + 1,
+                });
+            }
+        }
+
+        let yellow = board.yellow.count_ones() as usize;
+        let red = board.red.count_ones() as usize;
+        if yellow != red && yellow != red + 1 {
+            return Err(ParseBoardError::ImpossibleCounts { yellow, red });
+        }
+
+        board.history = board
+            .reconstruct_history()
+            .ok_or(ParseBoardError::Unreachable)?;
+
+        Ok(board)
+    }
+}
+
+impl<const W: usize, const H: usize, const N: usize> Display for Board<W, H, N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (r, row) in self.board.iter().enumerate().rev() {
+        let [yellow_threats, red_threats] = self.threats;
+
+        for r in (0..H).rev() {
             write!(f, "|")?;
-            for (c, cell) in row.iter().enumerate() {
-                let maybe_threat = self.threats.iter().find(|t| t.row == r && t.column == c);
-
-                match maybe_threat {
-                    Some(threat) if self.show_threats => match threat.color {
-                        Piece::Yellow => write!(f, " y")?,
-                        Piece::Red => write!(f, " r")?,
-                    },
-                    _ => {
-                        write!(f, " {}", cell)?;
-                    }
+            for c in 0..W {
+                let bit = Self::square_bit(r, c);
+
+                if self.show_threats && yellow_threats & bit != 0 {
+                    write!(f, " y")?;
+                } else if self.show_threats && red_threats & bit != 0 {
+                    write!(f, " r")?;
+                } else {
+                    write!(f, " {}", self.read_square(r, c))?;
                 }
             }
             writeln!(f, " |")?;
         }
 
         write!(f, "=")?;
-        for i in 0..WIDTH {
+        for i in 1..=W {
             write!(f, " {}", i)?;
         }
         writeln!(f, " = {}", self.turn_count)?;
@@ -462,6 +720,8 @@ mod tests {
     use super::*;
     use rstest::rstest;
 
+    const WIDTH: usize = <Board>::WIDTH;
+
     #[test]
     fn test_new() {
         let default_board = r"
@@ -473,9 +733,9 @@ mod tests {
         _______
         ";
 
-        let test_board = Board::from_str(default_board);
+        let test_board = <Board>::from_picture(default_board);
 
-        let expected_board = Board::default();
+        let expected_board = <Board>::default();
 
         assert_eq!(test_board, expected_board);
     }
@@ -504,16 +764,16 @@ mod tests {
         false
     )]
     fn test_is_empty(#[case] board_str: &str, #[case] expected: bool) {
-        let test_board = Board::from_str(board_str);
+        let test_board = <Board>::from_picture(board_str);
 
         assert_eq!(expected, test_board.is_empty());
     }
 
     #[test]
     fn test_apply_move() {
-        let mut board = Board::default();
+        let mut board = <Board>::default();
 
-        let expected_board = Board::from_str(
+        let expected_board = <Board>::from_picture(
             r"
         _______
         _______
@@ -595,9 +855,9 @@ mod tests {
         "
     )]
     fn test_remove_move(#[case] init: &str, #[case] column: usize, #[case] expected: &str) {
-        let mut init = Board::from_str(init);
+        let mut init = <Board>::from_picture(init);
 
-        let expected = Board::from_str(expected);
+        let expected = <Board>::from_picture(expected);
 
         init.remove_move(&column.into()).unwrap();
 
@@ -606,7 +866,7 @@ mod tests {
 
     #[test]
     fn test_remove_move_error() {
-        let mut init = Board::default();
+        let mut init = <Board>::default();
 
         assert_eq!(
             init.remove_move(&0usize.into()),
@@ -621,7 +881,7 @@ mod tests {
 
     #[test]
     fn test_apply_move_out_of_range() {
-        let mut board = Board::default();
+        let mut board = <Board>::default();
 
         let move_data = BoardMove {
             column: WIDTH,
@@ -634,13 +894,207 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case("", "")]
+    #[case("4", "4")]
+    #[case(" 4453 ", "4453")]
+    #[case("1111112", "1111112")]
+    fn test_moves_round_trip(#[case] moves: &str, #[case] expected: &str) {
+        let board = <Board>::from_moves(moves).unwrap();
+
+        assert_eq!(board.to_moves(), expected);
+        assert_eq!(board.history().len(), expected.len());
+    }
+
     #[test]
-    fn test_fill_empty() {
-        let mut board = Board::default();
+    fn test_from_moves() {
+        let expected = <Board>::from_picture(
+            r"
+        _______
+        _______
+        _______
+        _______
+        ___R___
+        ___YYR_
+        ",
+        );
+
+        assert_eq!(<Board>::from_moves("4456").unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("4x", BoardError::NotAColumn('x'))]
+    #[case("40", BoardError::NotAColumn('0'))]
+    #[case("48", BoardError::IllegalSequence { ply: 2, column: 8 })]
+    #[case("1111111", BoardError::IllegalSequence { ply: 7, column: 1 })]
+    #[case("12121213", BoardError::IllegalSequence { ply: 8, column: 3 })]
+    fn test_from_moves_error(#[case] moves: &str, #[case] expected: BoardError) {
+        assert_eq!(<Board>::from_moves(moves), Err(expected));
+    }
+
+    #[test]
+    fn test_play_moves_is_all_or_nothing() {
+        let mut board = <Board>::from_moves("44").unwrap();
+
+        assert!(board.play_moves("538").is_err());
+        assert_eq!(board.to_moves(), "44");
+
+        board.play_moves("53").unwrap();
+        assert_eq!(board.to_moves(), "4453");
+    }
+
+    #[rstest]
+    #[case(
+        r"
+        _______
+        _______
+        _______
+        _______
+        _______
+        _______
+        ",
+        ""
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        _______
+        _______
+        ___R___
+        ___YYR_
+        ",
+        "4456"
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        R______
+        YR_____
+        YR_____
+        YRY____
+        ",
+        "12121231"
+    )]
+    #[case(
+        r"
+        | _ _ _ _ _ _ _ |
+        | _ _ _ _ _ _ _ |
+        | _ _ _ _ _ _ _ |
+        | _ _ _ _ _ _ _ |
+        | _ _ _ R _ _ _ |
+        | _ _ _ Y Y R _ |
+        = 1 2 3 4 5 6 7 = 4
+        ",
+        "4456"
+    )]
+    fn test_parse(#[case] board_str: &str, #[case] moves: &str) {
+        let board: Board = board_str.parse().unwrap();
+
+        assert_eq!(board, <Board>::from_picture(board_str));
+        assert_eq!(<Board>::from_moves(&board.to_moves()).unwrap(), board);
+        assert_eq!(board.to_moves().len(), moves.len());
+    }
+
+    #[rstest]
+    #[case(
+        r"
+        _______
+        _______
+        _______
+        _______
+        _______
+        ",
+        ParseBoardError::WrongRowCount { expected: 6, found: 5 }
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        _______
+        ________
+        _______
+        _______
+        ",
+        ParseBoardError::WrongRowWidth { row: 3, expected: 7, found: 8 }
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        _______
+        _______
+        _______
+        ___X___
+        ",
+        ParseBoardError::UnexpectedSquare { row: 1, column: 4, square: 'X' }
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        ___Y___
+        _______
+        ___R___
+        ___Y___
+        ",
+        ParseBoardError::FloatingPiece { row: 4, column: 4 }
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        _______
+        _______
+        _______
+        ___RR__
+        ",
+        ParseBoardError::ImpossibleCounts { yellow: 0, red: 2 }
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        _______
+        _______
+        _______
+        YYY____
+        ",
+        ParseBoardError::ImpossibleCounts { yellow: 3, red: 0 }
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        RY_____
+        RY_____
+        RY_____
+        RY_____
+        ",
+        ParseBoardError::Unreachable
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        R______
+        R______
+        YYYY___
+        RRRY___
+        ",
+        ParseBoardError::Unreachable
+    )]
+    fn test_parse_error(#[case] board_str: &str, #[case] expected: ParseBoardError) {
+        assert_eq!(board_str.parse::<Board>(), Err(expected));
+    }
+
+    fn fill_empty<const W: usize, const H: usize, const N: usize>() {
+        let mut board = Board::<W, H, N>::default();
 
         assert!(board.is_empty());
 
-        for _ in 0..(HEIGHT * WIDTH) {
+        for _ in 0..(H * W) {
             let move_data = *board.list_moves().first().unwrap();
 
             assert_eq!(board.whos_to_play(), move_data.color.unwrap());
@@ -659,9 +1113,10 @@ mod tests {
         );
 
         assert!(board.is_full());
+        assert!(board.list_moves().is_empty());
 
-        for _ in 0..HEIGHT {
-            for j in 0..WIDTH {
+        for _ in 0..H {
+            for j in 0..W {
                 let move_data = BoardMove {
                     column: j,
                     color: None,
@@ -672,4 +1127,255 @@ mod tests {
         }
         assert!(board.is_empty());
     }
+
+    #[rstest]
+    #[case("", "4352617")]
+    #[case("12121", "1435267")]
+    #[case("121212", "1243567")]
+    fn test_ordered_moves(#[case] moves: &str, #[case] expected: &str) {
+        let board = <Board>::from_moves(moves).unwrap();
+
+        let ordered: String = board
+            .ordered_moves()
+            .iter()
+            .map(|m| char::from_digit(m.column as u32 + 1, 10).unwrap())
+            .collect();
+
+        assert_eq!(ordered, expected);
+    }
+
+    #[test]
+    fn test_fill_empty() {
+        fill_empty::<7, 6, 4>();
+        fill_empty::<8, 7, 4>();
+        fill_empty::<9, 7, 4>();
+        fill_empty::<6, 5, 4>();
+        fill_empty::<4, 4, 3>();
+    }
+
+    #[rstest]
+    #[case(
+        r"
+        _______
+        _______
+        _______
+        _______
+        RRR____
+        YYYY___
+        ",
+        GameEvaluation::Win(0)
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        ______R
+        ______R
+        Y_____R
+        YY____R
+        ",
+        GameEvaluation::Lose(0)
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        ___Y___
+        __YR___
+        _YRR___
+        YRRY___
+        ",
+        GameEvaluation::Win(0)
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        _R_____
+        _YR____
+        _YYR___
+        YYRYR__
+        ",
+        GameEvaluation::Lose(0)
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        _______
+        _______
+        _______
+        Y______
+        ",
+        GameEvaluation::OnGoing(4)
+    )]
+    #[case(
+        r"
+        _______
+        _______
+        _______
+        _______
+        _______
+        R__Y___
+        ",
+        GameEvaluation::OnGoing(3)
+    )]
+    fn test_evaluate(#[case] board_str: &str, #[case] expected: GameEvaluation) {
+        let board = <Board>::from_picture(board_str);
+
+        assert_eq!(board.evaluate(false), expected);
+    }
+
+    #[test]
+    fn test_no_connect_across_columns() {
+        // Three on top of one column and one at the bottom of the next
+        let board = <Board>::from_picture(
+            r"
+        Y______
+        Y______
+        Y______
+        R______
+        R______
+        RY_____
+        ",
+        );
+
+        assert!(!board.evaluate(false).is_terminal());
+    }
+
+    #[test]
+    fn test_threats() {
+        let mut board = <Board>::from_picture(
+            r"
+        _______
+        _______
+        _______
+        _______
+        _RR____
+        _YYY___
+        ",
+        );
+
+        board.calculate_threats();
+
+        assert_eq!(
+            board.threats,
+            [<Board>::square_bit(0, 0) | <Board>::square_bit(0, 4), 0]
+        );
+
+        board.clear_threats();
+
+        assert_eq!(board.threats, [0, 0]);
+    }
+
+    #[rstest]
+    #[case(
+        r"
+        _________
+        _________
+        _________
+        _________
+        _______YR
+        _______YR
+        Y______YR
+        ",
+        GameEvaluation::OnGoing(6)
+    )]
+    #[case(
+        r"
+        _________
+        _________
+        _________
+        ________R
+        _______YR
+        _______YR
+        Y______YR
+        ",
+        GameEvaluation::Lose(0)
+    )]
+    #[case(
+        r"
+        _____RRRR
+        _____YYYR
+        _____RYYY
+        _____YRRY
+        _____RYYR
+        _____YRRY
+        Y____YRYY
+        ",
+        GameEvaluation::Lose(0)
+    )]
+    fn test_evaluate_9x7(#[case] board_str: &str, #[case] expected: GameEvaluation) {
+        let board = Board::<9, 7, 4>::from_picture(board_str);
+
+        assert_eq!(board.evaluate(false), expected);
+    }
+
+    #[rstest]
+    #[case(
+        r"
+        _________
+        _________
+        _________
+        _________
+        _________
+        RRRR_____
+        YYYY_____
+        ",
+        false
+    )]
+    #[case(
+        r"
+        _________
+        _________
+        _________
+        _________
+        _________
+        RRRR_____
+        YYYYY____
+        ",
+        true
+    )]
+    fn test_connect_five(#[case] board_str: &str, #[case] yellow_wins: bool) {
+        let board = Board::<9, 7, 5>::from_picture(board_str);
+
+        assert_eq!(board.evaluate(false) == GameEvaluation::Win(0), yellow_wins);
+    }
+
+    #[test]
+    fn test_threats_8x7() {
+        let mut board = Board::<8, 7, 4>::from_picture(
+            r"
+        ________
+        ________
+        ________
+        ________
+        _______Y
+        ______RY
+        ____RRRY
+        ",
+        );
+
+        board.calculate_threats();
+
+        assert_eq!(
+            board.threats,
+            [
+                Board::<8, 7, 4>::square_bit(3, 7),
+                Board::<8, 7, 4>::square_bit(0, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display_6x5() {
+        let mut board = Board::<6, 5, 4>::default();
+        board.apply_move(&5usize.into()).unwrap();
+
+        let expected = "| _ _ _ _ _ _ |\n".repeat(4)
+            + &format!("| _ _ _ _ _ {} |\n", Piece::Yellow)
+            + "= 1 2 3 4 5 6 = 1\n";
+
+        assert_eq!(board.to_string(), expected);
+    }
 }
@@ -1,8 +1,10 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::bail;
 
 use super::piece::Piece;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Default)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Default, Hash)]
 pub struct BoardMove {
     pub column: usize,
     pub color: Option<Piece>,
@@ -32,11 +34,20 @@ impl From<usize> for BoardMove {
     }
 }
 
+/// The column, as players type it, 1 being the leftmost.
+impl Display for BoardMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.column + 1)
+    }
+}
+
 impl FromStr for BoardMove {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let column = s.parse::<usize>()?;
-        Ok(column.into())
+        match s.parse::<usize>()? {
+            0 => bail!("Columns are numbered from 1"),
+            column => Ok((column - 1).into()),
+        }
     }
 }
 
@@ -102,4 +113,20 @@ mod board_move_tests {
             }
         );
     }
+
+    #[test]
+    fn test_display_round_trip() {
+        let test_move: BoardMove = (4, Piece::Yellow).into();
+
+        assert_eq!(test_move.to_string(), "5");
+        assert_eq!(
+            test_move.to_string().parse::<BoardMove>().unwrap().column,
+            4
+        );
+    }
+
+    #[test]
+    fn test_parse_column_zero() {
+        assert!("0".parse::<BoardMove>().is_err());
+    }
 }
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod board;
 pub mod board_move;
 pub mod piece;
@@ -8,6 +8,23 @@ pub enum Piece {
     Red,
 }
 
+impl Piece {
+    pub fn other(&self) -> Self {
+        match self {
+            Piece::Yellow => Piece::Red,
+            Piece::Red => Piece::Yellow,
+        }
+    }
+
+    /// The colour in words, as written in the protocols and APIs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Piece::Yellow => "yellow",
+            Piece::Red => "red",
+        }
+    }
+}
+
 impl Display for Piece {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -10,6 +10,7 @@ pub enum Square {
 }
 
 impl Square {
+    #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
         *self == Self::Empty
     }
@@ -0,0 +1,451 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, Write},
+    panic::{self, AssertUnwindSafe},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail};
+
+use crate::{
+    board::{
+        board::{Board, BoardError},
+        board_move::BoardMove,
+        piece::Piece,
+    },
+    game::{Evaluate, GameEvaluation, MoM},
+    tree::{SearchReport, Tree},
+};
+
+type EngineTree<const W: usize, const H: usize, const N: usize> =
+    Tree<Board<W, H, N>, BoardMove, BoardError>;
+
+/// A line of the engine protocol sent to the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineCommand {
+    /// Set up the position after these columns, in the same notation as
+    /// `--position`
+    Position(String),
+    /// Search the position at most `depth` deep and for at most `move_time`
+    Go {
+        depth: Option<usize>,
+        move_time: Option<Duration>,
+    },
+    /// Stop the search and play the move from the deepest search finished
+    Stop,
+    /// Evaluate the position without searching
+    Eval,
+    IsReady,
+    Quit,
+}
+
+impl FromStr for EngineCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.split_whitespace();
+
+        let command = match words.next() {
+            Some("position") => {
+                let mut words = words.skip_while(|&word| word == "startpos");
+                match words.next() {
+                    None => EngineCommand::Position(String::new()),
+                    Some("moves") => EngineCommand::Position(words.collect()),
+                    Some(word) => bail!("Expected moves after position, got {}", word),
+                }
+            }
+            Some("go") => {
+                let mut depth = None;
+                let mut move_time = None;
+
+                while let Some(key) = words.next() {
+                    let value = words
+                        .next()
+                        .ok_or_else(|| anyhow!("Missing a value for {}", key))?;
+                    match key {
+                        "depth" => match value.parse()? {
+                            0 => bail!("The depth has to be at least 1"),
+                            value => depth = Some(value),
+                        },
+                        "movetime" => move_time = Some(Duration::from_millis(value.parse()?)),
+                        key => bail!("Unknown go option {}", key),
+                    }
+                }
+
+                EngineCommand::Go { depth, move_time }
+            }
+            Some("stop") => EngineCommand::Stop,
+            Some("eval") => EngineCommand::Eval,
+            Some("isready") => EngineCommand::IsReady,
+            Some("quit") => EngineCommand::Quit,
+            Some(command) => bail!("Unknown command {}", command),
+            None => bail!("Empty command"),
+        };
+
+        Ok(command)
+    }
+}
+
+/// The bot behind a line based text protocol, in the spirit of UCI, so other
+/// programs can drive it.
+///
+/// Searches run on their own thread so `stop` can be read while they think.
+/// Moves are written in the same notation as `--position`, 1 being the
+/// leftmost column, and scores are for the player to move.
+pub struct Engine<const W: usize, const H: usize, const N: usize, O> {
+    /// The position `position` plays its moves from
+    start: Board<W, H, N>,
+    board: Board<W, H, N>,
+    /// The tree, unless a search has it. A search puts it back before it
+    /// replies, so the next command finds it there.
+    tree: Arc<Mutex<Option<EngineTree<W, H, N>>>>,
+    search: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+    output: Arc<Mutex<O>>,
+    /// How deep `go` searches when it isn't given a depth or a move time
+    depth: usize,
+}
+
+impl<const W: usize, const H: usize, const N: usize, O: Write + Send + 'static> Engine<W, H, N, O> {
+    pub fn new(start: Board<W, H, N>, tree: EngineTree<W, H, N>, depth: usize, output: O) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        Self {
+            board: start.clone(),
+            start,
+            tree: Arc::new(Mutex::new(Some(tree.with_stop_signal(stop.clone())))),
+            search: None,
+            stop,
+            output: Arc::new(Mutex::new(output)),
+            depth,
+        }
+    }
+
+    /// Answer commands from `input` until `quit` or the end of the input,
+    /// which waits for a running search to finish.
+    pub fn run(&mut self, input: impl BufRead) -> anyhow::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let result = line.parse().and_then(|command| self.handle(command));
+            match result {
+                Ok(true) => {}
+                Ok(false) => return Ok(()),
+                Err(e) => send(&self.output, format!("error {}", e)),
+            }
+        }
+
+        self.join_search();
+
+        Ok(())
+    }
+
+    /// Carry out `command`, returning whether to read another.
+    fn handle(&mut self, command: EngineCommand) -> anyhow::Result<bool> {
+        match command {
+            EngineCommand::Position(moves) => {
+                if self.tree.lock().unwrap().is_none() {
+                    bail!("Can't change the position during a search");
+                }
+
+                let mut board = self.start.clone();
+                board.play_moves(&moves)?;
+                self.board = board;
+            }
+            EngineCommand::Go { depth, move_time } => self.go(depth, move_time)?,
+            EngineCommand::Stop => self.stop_search(),
+            EngineCommand::Eval => send(
+                &self.output,
+                format!("eval {}", score(self.board.evaluate(false), &self.board)),
+            ),
+            EngineCommand::IsReady => send(&self.output, "readyok"),
+            EngineCommand::Quit => {
+                self.stop_search();
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn go(&mut self, depth: Option<usize>, move_time: Option<Duration>) -> anyhow::Result<()> {
+        if self.board.is_over() {
+            send(&self.output, "bestmove none");
+            return Ok(());
+        }
+
+        let Some(mut tree) = self.tree.lock().unwrap().take() else {
+            bail!("Already searching");
+        };
+        // The last search has put the tree back, but may not have returned
+        self.join_search();
+
+        let max_depth = match (depth, move_time) {
+            (Some(depth), _) => depth,
+            (None, Some(_)) => usize::MAX,
+            (None, None) => self.depth,
+        };
+
+        let mut board = self.board.clone();
+        let output = self.output.clone();
+        let slot = self.tree.clone();
+        self.stop.store(false, Ordering::Relaxed);
+
+        self.search = Some(thread::spawn(move || {
+            let start = Instant::now();
+            let to_play = board.clone();
+
+            // A search that goes wrong mustn't take the tree, or the engine,
+            // down with it
+            let best = panic::catch_unwind(AssertUnwindSafe(|| {
+                tree.get_best_move_deepening(&mut board, max_depth, move_time, |report| {
+                    send(&output, info(report, &to_play, start.elapsed()));
+                })
+            }));
+            *slot.lock().unwrap() = Some(tree);
+            match best {
+                Ok(best) => send(&output, format!("bestmove {}", notation(&best))),
+                Err(_) => send(&output, "error The search failed"),
+            }
+        }));
+
+        Ok(())
+    }
+
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.join_search();
+    }
+
+    /// Wait for the search thread to finish.
+    fn join_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            if search.join().is_err() {
+                send(&self.output, "error The search failed");
+            }
+        }
+    }
+}
+
+/// Write a line of the protocol. There is nobody to tell if the output has
+/// gone, so that is ignored.
+fn send<O: Write>(output: &Mutex<O>, line: impl Display) {
+    let mut output = output.lock().unwrap();
+    let _ = writeln!(output, "{}", line);
+    let _ = output.flush();
+}
+
+/// A move as the protocol writes it.
+fn notation(m: &BoardMove) -> usize {
+    m.column + 1
+}
+
+/// What a finished depth found, e.g. `info depth 6 score 42 nodes 1200 time 3 pv 4 4 3`.
+fn info<const W: usize, const H: usize, const N: usize>(
+    report: &SearchReport<BoardMove>,
+    board: &Board<W, H, N>,
+    elapsed: Duration,
+) -> String {
+    let pv: Vec<String> = report.pv.iter().map(|m| notation(m).to_string()).collect();
+
+    format!(
+        "info depth {} score {} nodes {} time {} pv {}",
+        report.depth,
+        score(report.eval, board),
+        report.nodes,
+        elapsed.as_millis(),
+        pv.join(" ")
+    )
+}
+
+/// An evaluation for the player to move on `board`, as a number or as
+/// `mate` and how many of the winner's moves a forced result is away,
+/// negative when the player to move loses.
+fn score<const W: usize, const H: usize, const N: usize>(
+    eval: GameEvaluation,
+    board: &Board<W, H, N>,
+) -> String {
+    let maxing = matches!(board.min_or_maxing(), MoM::Max);
+    let sign = if maxing { 1 } else { -1 };
+
+    match eval {
+        GameEvaluation::OnGoing(eval) => (sign * eval).to_string(),
+        _ => match eval.forced_win() {
+            Some((winner, moves)) => {
+                let sign = if winner == Piece::Yellow { sign } else { -sign };
+                format!("mate {}", sign * moves as isize)
+            }
+            None => "0".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Algorithm;
+    use rstest::rstest;
+
+    /// Run `input` through an engine and return what it wrote.
+    fn run(input: &str) -> Vec<String> {
+        let tree = Tree::new(1, Algorithm::AlphaBeta, false, 1024 * 1024);
+        let mut engine: Engine<7, 6, 4, Vec<u8>> = Engine::new(Board::default(), tree, 5, vec![]);
+
+        engine.run(input.as_bytes()).unwrap();
+
+        let output = engine.output.lock().unwrap();
+        String::from_utf8(output.clone())
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[rstest]
+    #[case("position", EngineCommand::Position("".into()))]
+    #[case("position startpos", EngineCommand::Position("".into()))]
+    #[case("position moves 4453", EngineCommand::Position("4453".into()))]
+    #[case("position startpos moves 4 4 5 3", EngineCommand::Position("4453".into()))]
+    #[case("go", EngineCommand::Go { depth: None, move_time: None })]
+    #[case("go depth 8", EngineCommand::Go { depth: Some(8), move_time: None })]
+    #[case(
+        "go movetime 500 depth 3",
+        EngineCommand::Go { depth: Some(3), move_time: Some(Duration::from_millis(500)) }
+    )]
+    #[case("  stop ", EngineCommand::Stop)]
+    #[case("eval", EngineCommand::Eval)]
+    #[case("isready", EngineCommand::IsReady)]
+    #[case("quit", EngineCommand::Quit)]
+    fn test_parse(#[case] line: &str, #[case] expected: EngineCommand) {
+        assert_eq!(line.parse::<EngineCommand>().unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("position 4453")]
+    #[case("go depth")]
+    #[case("go depth deep")]
+    #[case("go depth 0")]
+    #[case("go nodes 100")]
+    #[case("think")]
+    fn test_parse_invalid(#[case] line: &str) {
+        assert!(line.parse::<EngineCommand>().is_err());
+    }
+
+    #[test]
+    fn test_go_depth() {
+        let output = run("position moves 112233\ngo depth 4\n");
+
+        // Yellow wins at once, so there's no need to search past depth 2
+        assert_eq!(output.len(), 2);
+        assert!(output[0].starts_with("info depth 2 score mate 1 "));
+        assert!(output[0].ends_with(" pv 4"));
+        assert_eq!(output[1], "bestmove 4");
+    }
+
+    #[test]
+    fn test_go_depths() {
+        let output = run("position moves 44\ngo depth 5\n");
+
+        let depths: Vec<&str> = output
+            .iter()
+            .filter_map(|line| line.strip_prefix("info depth "))
+            .map(|line| line.split(' ').next().unwrap())
+            .collect();
+        assert_eq!(depths, ["2", "3", "4", "5"]);
+        assert!(output.last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn test_go_move_time() {
+        let start = Instant::now();
+        let output = run("go movetime 100\n");
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(output[0].starts_with("info depth 2 "));
+        assert!(output.last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn test_commands_right_after_bestmove() {
+        let tree = Tree::new(1, Algorithm::AlphaBeta, false, 0);
+        let mut engine: Engine<7, 6, 4, Vec<u8>> = Engine::new(Board::default(), tree, 5, vec![]);
+        let output = engine.output.clone();
+
+        // Like a client, send the next position as soon as the reply comes
+        for searches in 1..=100 {
+            let moves = "4".repeat(searches % 6);
+            engine
+                .handle(format!("position moves {}", moves).parse().unwrap())
+                .unwrap();
+            engine.handle("go depth 2".parse().unwrap()).unwrap();
+
+            while String::from_utf8_lossy(&output.lock().unwrap())
+                .matches("bestmove")
+                .count()
+                < searches
+            {
+                thread::yield_now();
+            }
+        }
+    }
+
+    #[rstest]
+    #[case("go movetime 60000\nstop\nisready\n")]
+    #[case("go depth 40\nquit\n")]
+    fn test_stop(#[case] input: &str) {
+        let start = Instant::now();
+        let output = run(input);
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        let best = output
+            .iter()
+            .find_map(|line| line.strip_prefix("bestmove "))
+            .unwrap();
+        assert!(("1"..="7").contains(&best));
+    }
+
+    #[rstest]
+    // Yellow has an open three on the bottom row, Red can only block one end
+    #[case("22334", "mate -1")]
+    // Yellow makes that open three and wins with its second move
+    #[case("2233", "mate 2")]
+    fn test_score_for_player_to_move(#[case] moves: &str, #[case] expected: &str) {
+        let output = run(&format!("position moves {}\ngo depth 4\n", moves));
+
+        let info = &output[output.len() - 2];
+        assert!(info.contains(&format!(" score {} ", expected)), "{}", info);
+    }
+
+    #[rstest]
+    #[case("eval\n", "eval 0")]
+    #[case("position moves 1212121\neval\n", "eval mate 0")]
+    #[case("position moves 1212121\ngo\n", "bestmove none")]
+    #[case("isready\n", "readyok")]
+    #[case(
+        "position moves 8\n",
+        "error Move 1 (column 8) of the sequence can't be played."
+    )]
+    #[case("think\n", "error Unknown command think")]
+    #[case("go depth 0\n", "error The depth has to be at least 1")]
+    fn test_reply(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(run(input), vec![expected.to_string()]);
+    }
+
+    #[test]
+    fn test_illegal_position_keeps_board() {
+        let output = run("position moves 112233\nposition moves 1111111\ngo depth 2\n");
+
+        assert!(output[0].starts_with("error "));
+        assert_eq!(output.last().unwrap(), "bestmove 4");
+    }
+}
@@ -1,9 +1,20 @@
 use std::{
     cmp::Ordering,
     fmt::{Debug, Display},
+    time::{Duration, Instant},
 };
 
-use crate::board::{board::Board, board_move::BoardMove};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{
+        board::{Board, BoardError},
+        board_move::BoardMove,
+        piece::Piece,
+    },
+    observer::GameObserver,
+    tree::SearchReport,
+};
 
 pub trait MovePiece {
     type MoveData;
@@ -13,6 +24,11 @@ pub trait MovePiece {
     fn remove_move(&mut self, move_data: &Self::MoveData) -> Result<(), Self::MoveError>;
     fn is_move_valid(&self, move_data: &Self::MoveData) -> bool;
     fn list_moves(&self) -> Vec<Self::MoveData>;
+    /// The legal moves with the likeliest to be good first, for searches that
+    /// prune.
+    fn ordered_moves(&self) -> Vec<Self::MoveData> {
+        self.list_moves()
+    }
 }
 
 pub enum MoM {
@@ -20,36 +36,85 @@ pub enum MoM {
     Max,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd)]
+/// How a position looks for the maximising player.
+///
+/// Wins and losses count the plies until the game ends, so a quicker win is
+/// better than a slower one and a slower loss better than a quicker one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GameEvaluation {
     MinusInfinity,
-    Lose,
+    Lose(usize),
     Draw,
     OnGoing(isize),
-    Win,
+    Win(usize),
     PlusInfinity,
 }
 
 impl GameEvaluation {
     pub fn is_terminal(&self) -> bool {
-        if let Self::OnGoing(_) = self {
-            false
-        } else {
-            true
+        !matches!(self, Self::OnGoing(_))
+    }
+
+    /// The same result `plies` further away.
+    pub fn add_plies(self, plies: usize) -> Self {
+        match self {
+            Self::Win(n) => Self::Win(n + plies),
+            Self::Lose(n) => Self::Lose(n + plies),
+            eval => eval,
+        }
+    }
+
+    /// The same result `plies` closer.
+    pub fn sub_plies(self, plies: usize) -> Self {
+        match self {
+            Self::Win(n) => Self::Win(n.saturating_sub(plies)),
+            Self::Lose(n) => Self::Lose(n.saturating_sub(plies)),
+            eval => eval,
+        }
+    }
+
+    /// Who wins and in how many of their own moves, if the result is forced.
+    pub fn forced_win(&self) -> Option<(Piece, usize)> {
+        match *self {
+            Self::Win(plies) => Some((Piece::Yellow, plies.div_ceil(2))),
+            Self::Lose(plies) => Some((Piece::Red, plies.div_ceil(2))),
+            _ => None,
+        }
+    }
+}
+
+/// Scores are signed for the maximising player, and a forced result is `M`
+/// and its plies, e.g. `+M3` for a win in three.
+impl Display for GameEvaluation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MinusInfinity => write!(f, "-inf"),
+            Self::Lose(plies) => write!(f, "-M{}", plies),
+            Self::Draw => write!(f, "draw"),
+            Self::OnGoing(eval) => write!(f, "{:+}", eval),
+            Self::Win(plies) => write!(f, "+M{}", plies),
+            Self::PlusInfinity => write!(f, "+inf"),
         }
     }
 }
 
+impl PartialOrd for GameEvaluation {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Ord for GameEvaluation {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         match (self, other) {
             (Self::MinusInfinity, _) | (_, Self::PlusInfinity) => Ordering::Less,
             (Self::PlusInfinity, _) | (_, Self::MinusInfinity) => Ordering::Greater,
-            (Self::Lose, Self::Lose) | (Self::Win, Self::Win) | (Self::Draw, Self::Draw) => {
-                Ordering::Equal
-            }
-            (Self::Lose, _) | (_, Self::Win) => Ordering::Less,
-            (Self::Win, _) | (_, Self::Lose) => Ordering::Greater,
+            (Self::Draw, Self::Draw) => Ordering::Equal,
+            // The quicker win and the slower loss are better
+            (Self::Win(s), Self::Win(o)) => o.cmp(s),
+            (Self::Lose(s), Self::Lose(o)) => s.cmp(o),
+            (Self::Lose(_), _) | (_, Self::Win(_)) => Ordering::Less,
+            (Self::Win(_), _) | (_, Self::Lose(_)) => Ordering::Greater,
             (Self::Draw, Self::OnGoing(_)) => Ordering::Less,
             (Self::OnGoing(_), Self::Draw) => Ordering::Greater,
             (Self::OnGoing(s), Self::OnGoing(o)) => s.cmp(o),
@@ -60,117 +125,454 @@ impl Ord for GameEvaluation {
 pub trait Evaluate {
     fn min_or_maxing(&self) -> MoM;
     fn evaluate(&self, use_threats: bool) -> GameEvaluation;
+    /// Whether the game has ended, which can be quicker to tell than a full
+    /// evaluation.
+    fn is_over(&self) -> bool {
+        self.evaluate(false).is_terminal()
+    }
 }
 
 pub trait GameBoard<D, E>: MovePiece<MoveData = D, MoveError = E> + Evaluate + Display {}
 impl<D, E, T: MovePiece<MoveData = D, MoveError = E> + Evaluate + Display> GameBoard<D, E> for T {}
 
-pub struct Game {
-    pub board: Board,
-    pub player1: Box<dyn Play>,
-    pub player2: Box<dyn Play>,
+pub struct Game<const W: usize = 7, const H: usize = 6, const N: usize = 4> {
+    pub board: Board<W, H, N>,
+    pub player1: Box<dyn Play<W, H, N>>,
+    pub player2: Box<dyn Play<W, H, N>>,
+    /// Told about everything that happens, games without any run silently
+    pub observers: Vec<Box<dyn GameObserver<W, H, N>>>,
+}
+
+/// What a player wants to do on their turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerAction {
+    Move(BoardMove),
+    /// Take back the last round, this player's last move and the reply to it
+    Undo,
+    /// Give up the game, saying why
+    Forfeit(String),
 }
 
-pub trait Play: Display {
-    fn get_move(&mut self, board: Board) -> BoardMove;
+pub trait Play<const W: usize = 7, const H: usize = 6, const N: usize = 4>: Display {
+    fn get_move(&mut self, board: Board<W, H, N>) -> BoardMove;
+    /// Players that can do more than move on their turn override this.
+    fn get_action(&mut self, board: Board<W, H, N>) -> PlayerAction {
+        PlayerAction::Move(self.get_move(board))
+    }
     fn needs_to_see_board(&self) -> bool;
     fn should_announce_move(&self) -> bool;
+    /// What the search behind the last move found, for players that search.
+    fn search_report(&self) -> Option<SearchReport<BoardMove>> {
+        None
+    }
+    /// The game is over, for players that need to know, e.g. to pass on the
+    /// last move.
+    fn on_game_over(&mut self, _result: &GameResult<W, H, N>) {}
 }
 
-impl Game {
-    pub fn game_loop(&mut self) {
-        loop {
-            let done = self.game_loops();
-            if done {
-                break;
-            }
+/// One move of a game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveRecord {
+    pub move_data: BoardMove,
+    /// How long the player took to choose the move
+    pub time: Duration,
+    /// The board's evaluation after the move
+    pub eval: GameEvaluation,
+    /// What the player's search found, if it searched
+    pub search: Option<SearchReport<BoardMove>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ending {
+    Connect,
+    Draw,
+    /// A player tried a move the board didn't allow and forfeited
+    IllegalMove,
+    /// A player gave up, e.g. an external engine that crashed or ran out of
+    /// time
+    Forfeit,
+}
+
+/// How a game went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameResult<const W: usize = 7, const H: usize = 6, const N: usize = 4> {
+    pub winner: Option<Piece>,
+    pub ending: Ending,
+    /// The moves played by the players, not any the board started with
+    pub moves: Vec<MoveRecord>,
+    /// How many moves the whole game took, counting any the board started with
+    pub plies: usize,
+    pub board: Board<W, H, N>,
+}
+
+impl<const W: usize, const H: usize, const N: usize> Game<W, H, N> {
+    /// Play the game out and say how it went.
+    pub fn run(&mut self) -> GameResult<W, H, N> {
+        for observer in &mut self.observers {
+            observer.on_game_start(&self.board, self.player1.as_ref(), self.player2.as_ref());
         }
-    }
 
-    fn game_loops(&mut self) -> bool {
-        for player_num in &[1, 2] {
-            let done = self.player_loop(*player_num);
-            if done {
-                return done;
+        let mut moves = vec![];
+
+        let mut eval = self.board.evaluate(false);
+
+        // The board may start part way through, or even at the end of, a game
+        while !eval.is_terminal() {
+            let color = match self.board.min_or_maxing() {
+                MoM::Max => Piece::Yellow,
+                MoM::Min => Piece::Red,
+            };
+            let player = match color {
+                Piece::Yellow => &mut self.player1,
+                Piece::Red => &mut self.player2,
+            };
+
+            for observer in &mut self.observers {
+                observer.on_move_requested(&self.board, player.as_ref());
+            }
+
+            let start = Instant::now();
+            let action = player.get_action(self.board.clone());
+            let time = start.elapsed();
+
+            let move_data = match action {
+                PlayerAction::Move(move_data) => move_data,
+                PlayerAction::Undo => {
+                    // Only the records of this run's moves that came off the
+                    // board go, not more if it went back past where it started
+                    let plies = self.board.history().len();
+                    let undone = self.undo_round();
+                    let taken_back = plies - self.board.history().len();
+                    moves.truncate(moves.len().saturating_sub(taken_back));
+                    for observer in &mut self.observers {
+                        observer.on_undo(&self.board, &undone);
+                    }
+                    continue;
+                }
+                PlayerAction::Forfeit(reason) => {
+                    for observer in &mut self.observers {
+                        observer.on_forfeit(&self.board, player.as_ref(), &reason);
+                    }
+                    return self.finish(Some(color.other()), Ending::Forfeit, moves);
+                }
+            };
+
+            if let Err(e) = self.board.apply_move(&move_data) {
+                for observer in &mut self.observers {
+                    observer.on_illegal_move(&self.board, player.as_ref(), &move_data, &e);
+                }
+                return self.finish(Some(color.other()), Ending::IllegalMove, moves);
+            }
+
+            eval = self.board.evaluate(false);
+            let record = MoveRecord {
+                move_data,
+                time,
+                eval,
+                search: player.search_report(),
+            };
+
+            for observer in &mut self.observers {
+                observer.on_move_played(&self.board, player.as_ref(), &record);
+                observer.on_evaluation(&self.board, eval);
             }
+            moves.push(record);
+        }
+
+        match eval {
+            GameEvaluation::Win(_) => self.finish(Some(Piece::Yellow), Ending::Connect, moves),
+            GameEvaluation::Lose(_) => self.finish(Some(Piece::Red), Ending::Connect, moves),
+            _ => self.finish(None, Ending::Draw, moves),
         }
-        false
     }
 
-    fn player_loop(&mut self, player_num: u32) -> bool {
-        let p = match player_num {
-            1 => &mut self.player1,
-            2 => &mut self.player2,
-            _ => unreachable!(),
-        };
-        if p.needs_to_see_board() {
-            self.board.calculate_threats();
-            println!("{}", self.board);
-            self.board.clear_threats();
-            println!("Please enter your move:");
-        }
-
-        let move_data = p.get_move(self.board.clone());
-
-        if p.should_announce_move() {
-            println!("Played {:?}", move_data);
-        }
-
-        let move_result = self.board.apply_move(&move_data);
-        match move_result {
-            Ok(t) => t,
-            Err(e) => {
-                println!(
-                    "Move {:?} for player {} failed becuase: {:?}.",
-                    move_data, p, e
-                );
-                println!("Exiting");
-                println!("{}", self.board);
-                return true;
-            }
+    fn finish(
+        &mut self,
+        winner: Option<Piece>,
+        ending: Ending,
+        moves: Vec<MoveRecord>,
+    ) -> GameResult<W, H, N> {
+        let result = GameResult {
+            winner,
+            ending,
+            moves,
+            plies: self.board.history().len(),
+            board: self.board.clone(),
         };
 
-        println!();
-        println!();
+        self.player1.on_game_over(&result);
+        self.player2.on_game_over(&result);
 
-        println!("Evaluating current board state");
-        let eval = self.board.evaluate(false);
+        for observer in &mut self.observers {
+            observer.on_game_over(&result, self.player1.as_ref(), self.player2.as_ref());
+        }
 
-        match eval {
-            GameEvaluation::Win => {
-                print_boardered(&format!("{} beat {}!", self.player1, self.player2));
-                println!("{}", self.board);
-                true
-            }
-            GameEvaluation::Lose => {
-                print_boardered(&format!("{} beat {}!", self.player2, self.player1));
-                println!("{}", self.board);
-                true
-            }
-            GameEvaluation::Draw => {
-                print_boardered("It's a Draw!");
-                println!("{}", self.board);
-                true
-            }
-            GameEvaluation::OnGoing(val) => {
-                println!("The game continues with the eval {}.", val);
-                println!();
-                false
-            }
-            _ => {
-                println!("Invalid state");
-                println!();
-                true
-            }
+        result
+    }
+
+    /// Take back the last two moves so whoever is to play gets their last move
+    /// back.
+    pub fn undo_round(&mut self) -> Result<(), BoardError> {
+        let history = self.board.history();
+        if history.len() < 2 {
+            return Err(BoardError::NothingToUndo);
+        }
+
+        for _ in 0..2 {
+            let column = *self.board.history().last().unwrap();
+            self.board.remove_move(&column.into())?;
         }
+
+        Ok(())
     }
 }
 
-fn print_boardered(s: &str) {
-    let border = "*".repeat(20);
-    println!();
-    println!("{}", border);
-    println!("{}", s);
-    println!("{}", border);
-    println!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        player::{bot::Bot, random::Random},
+        tree::Algorithm,
+    };
+    use rstest::rstest;
+    use std::{cell::RefCell, rc::Rc};
+
+    fn game(moves: &str) -> Game {
+        Game {
+            board: Board::from_moves(moves).unwrap(),
+            player1: Box::new(Random {
+                color: Piece::Yellow,
+            }),
+            player2: Box::new(Random { color: Piece::Red }),
+            observers: vec![],
+        }
+    }
+
+    fn bot(color: Piece, depth: usize) -> Box<Bot> {
+        Box::new(Bot::new(
+            color,
+            depth,
+            Algorithm::AlphaBeta,
+            false,
+            None,
+            0,
+            1,
+        ))
+    }
+
+    #[test]
+    fn test_run() {
+        let mut game = game("");
+        game.player1 = bot(Piece::Yellow, 4);
+
+        let result = game.run();
+
+        assert_eq!(result.plies, result.moves.len());
+        assert_eq!(result.board, game.board);
+        assert!(result.moves.last().unwrap().eval.is_terminal());
+        assert!(result.moves[0].search.is_some());
+        assert!(result
+            .moves
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .all(|m| m.search.is_none()));
+
+        let moves: String = result
+            .moves
+            .iter()
+            .map(|m| char::from_digit(m.move_data.column as u32 + 1, 10).unwrap())
+            .collect();
+        assert_eq!(result.board.to_moves(), moves);
+
+        match result.winner {
+            Some(_) => assert_eq!(result.ending, Ending::Connect),
+            None => assert_eq!(result.ending, Ending::Draw),
+        }
+    }
+
+    #[rstest]
+    #[case("121212", Some(Piece::Yellow), 1, 7)]
+    #[case("1212121", Some(Piece::Yellow), 0, 7)]
+    #[case("2121213", Some(Piece::Red), 1, 8)]
+    fn test_run_from_position(
+        #[case] moves: &str,
+        #[case] winner: Option<Piece>,
+        #[case] played: usize,
+        #[case] plies: usize,
+    ) {
+        let mut game = game(moves);
+        game.player1 = bot(Piece::Yellow, 2);
+        game.player2 = bot(Piece::Red, 2);
+
+        let result = game.run();
+
+        assert_eq!(result.winner, winner);
+        assert_eq!(result.ending, Ending::Connect);
+        assert_eq!(result.moves.len(), played);
+        assert_eq!(result.plies, plies);
+    }
+
+    /// Writes down every event it sees.
+    #[derive(Default)]
+    struct EventLog(Rc<RefCell<Vec<String>>>);
+
+    impl GameObserver for EventLog {
+        fn on_game_start(&mut self, board: &Board, _: &dyn Play, _: &dyn Play) {
+            self.0
+                .borrow_mut()
+                .push(format!("start {}", board.to_moves()));
+        }
+
+        fn on_move_requested(&mut self, _: &Board, player: &dyn Play) {
+            self.0.borrow_mut().push(format!("requested {}", player));
+        }
+
+        fn on_move_played(&mut self, _: &Board, _: &dyn Play, record: &MoveRecord) {
+            let column = record.move_data.column;
+            self.0.borrow_mut().push(format!("played {}", column));
+        }
+
+        fn on_evaluation(&mut self, _: &Board, eval: GameEvaluation) {
+            self.0.borrow_mut().push(format!("eval {:?}", eval));
+        }
+
+        fn on_game_over(&mut self, result: &GameResult, _: &dyn Play, _: &dyn Play) {
+            self.0
+                .borrow_mut()
+                .push(format!("over {:?}", result.winner));
+        }
+    }
+
+    #[test]
+    fn test_observers() {
+        let mut game = game("121212");
+        game.player1 = bot(Piece::Yellow, 2);
+
+        let logs = [Rc::default(), Rc::default()];
+        game.observers = logs
+            .iter()
+            .map(|log| Box::new(EventLog(Rc::clone(log))) as Box<dyn GameObserver>)
+            .collect();
+
+        game.run();
+
+        let expected = [
+            "start 121212",
+            "requested Y",
+            "played 0",
+            "eval Win(0)",
+            "over Some(Yellow)",
+        ];
+
+        for log in logs {
+            assert_eq!(*log.borrow(), expected);
+        }
+    }
+
+    #[rstest]
+    #[case(GameEvaluation::Win(1), GameEvaluation::Win(3))]
+    #[case(GameEvaluation::Win(9), GameEvaluation::OnGoing(1000))]
+    #[case(GameEvaluation::Lose(8), GameEvaluation::Lose(2))]
+    #[case(GameEvaluation::Draw, GameEvaluation::Lose(40))]
+    #[case(GameEvaluation::PlusInfinity, GameEvaluation::Win(0))]
+    fn test_eval_order(#[case] better: GameEvaluation, #[case] worse: GameEvaluation) {
+        assert!(better > worse);
+        assert!(worse < better);
+    }
+
+    #[rstest]
+    #[case(GameEvaluation::OnGoing(37), "+37")]
+    #[case(GameEvaluation::OnGoing(-5), "-5")]
+    #[case(GameEvaluation::OnGoing(0), "+0")]
+    #[case(GameEvaluation::Win(3), "+M3")]
+    #[case(GameEvaluation::Lose(2), "-M2")]
+    #[case(GameEvaluation::Draw, "draw")]
+    fn test_eval_display(#[case] eval: GameEvaluation, #[case] expected: &str) {
+        assert_eq!(eval.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case(GameEvaluation::Win(1), Some((Piece::Yellow, 1)))]
+    #[case(GameEvaluation::Win(4), Some((Piece::Yellow, 2)))]
+    #[case(GameEvaluation::Lose(5), Some((Piece::Red, 3)))]
+    #[case(GameEvaluation::Draw, None)]
+    #[case(GameEvaluation::OnGoing(3), None)]
+    fn test_forced_win(#[case] eval: GameEvaluation, #[case] expected: Option<(Piece, usize)>) {
+        assert_eq!(eval.forced_win(), expected);
+    }
+
+    /// A player that takes the actions it's given, then gives up.
+    struct Scripted(Piece, Vec<PlayerAction>);
+
+    impl Display for Scripted {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl Play for Scripted {
+        fn get_move(&mut self, _: Board) -> BoardMove {
+            unreachable!()
+        }
+
+        fn get_action(&mut self, _: Board) -> PlayerAction {
+            match self.1.is_empty() {
+                true => PlayerAction::Forfeit("Out of moves".into()),
+                false => self.1.remove(0),
+            }
+        }
+
+        fn needs_to_see_board(&self) -> bool {
+            false
+        }
+
+        fn should_announce_move(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_undo_on_resumed_game() {
+        let mut game = game("4453");
+        game.player1 = Box::new(Scripted(
+            Piece::Yellow,
+            vec![
+                PlayerAction::Move(0.into()),
+                PlayerAction::Undo,
+                PlayerAction::Move(6.into()),
+            ],
+        ));
+        game.player2 = Box::new(Scripted(
+            Piece::Red,
+            vec![
+                PlayerAction::Move(1.into()),
+                PlayerAction::Undo,
+                PlayerAction::Move(1.into()),
+            ],
+        ));
+
+        let result = game.run();
+
+        // Red's undo went back past the start, taking Yellow's 7 with it
+        assert_eq!(result.board.to_moves(), "4452");
+        let columns: Vec<_> = result.moves.iter().map(|m| m.move_data.column).collect();
+        assert_eq!(columns, [1]);
+    }
+
+    #[test]
+    fn test_undo_round() {
+        let mut game = game("44531");
+
+        game.undo_round().unwrap();
+        assert_eq!(game.board, Board::from_moves("445").unwrap());
+        assert_eq!(game.board.to_moves(), "445");
+
+        game.undo_round().unwrap();
+        assert_eq!(game.board.to_moves(), "4");
+
+        assert_eq!(game.undo_round(), Err(BoardError::NothingToUndo));
+        assert_eq!(game.board.to_moves(), "4");
+    }
 }
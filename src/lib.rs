@@ -1,4 +1,14 @@
 pub mod board;
+pub mod engine;
 pub mod game;
+pub mod mcts;
+pub mod network;
+pub mod observer;
 pub mod player;
+pub mod record;
+pub mod server;
+pub mod solver;
+pub mod tournament;
+pub mod transposition;
 pub mod tree;
+pub mod tui;
@@ -1,94 +1,736 @@
 use quatre_con::{
-    board::{board::Board, piece::Piece},
-    game::{Game, Play},
-    player::{bot::Bot, human::Human, random::Random},
-    tree::Algorithm,
+    board::{
+        board::{Board, BoardError},
+        board_move::BoardMove,
+        piece::Piece,
+    },
+    engine::Engine,
+    game::{Evaluate, Game, MoM, Play},
+    mcts,
+    network::{self, parse_color, Connection, Setup},
+    observer::{ConsoleObserver, GameObserver},
+    player::{
+        config::{parse_duration, PlayerConfig},
+        remote::Remote,
+    },
+    record::{GameRecord, SaveFile},
+    server::WebGame,
+    solver::{Outcome, Solver},
+    tournament::Tournament,
+    tree::{Algorithm, Tree},
+    tui::{self, Screen, Terminal, TuiObserver},
 };
 
-use clap::Parser;
+use std::{
+    fs, io,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context};
+use clap::{Parser, Subcommand};
+
+/// Call `$run::<W, H, N>($args)` for the board size that was asked for.
+///
+/// Every size listed here gets its own copy of the game, so keep the list to
+/// the variants people actually play.
+macro_rules! with_board_size {
+    ($width:expr, $height:expr, $connect:expr, $run:ident($($args:expr),*)) => {
+        match ($width, $height, $connect) {
+            (7, 6, 4) => $run::<7, 6, 4>($($args),*),
+            (8, 7, 4) => $run::<8, 7, 4>($($args),*),
+            (9, 7, 4) => $run::<9, 7, 4>($($args),*),
+            (6, 5, 4) => $run::<6, 5, 4>($($args),*),
+            (6, 7, 4) => $run::<6, 7, 4>($($args),*),
+            (7, 7, 4) => $run::<7, 7, 4>($($args),*),
+            (5, 4, 4) => $run::<5, 4, 4>($($args),*),
+            (4, 4, 3) => $run::<4, 4, 3>($($args),*),
+            (9, 7, 5) => $run::<9, 7, 5>($($args),*),
+            (w, h, n) => bail!(
+                "Unsupported board size {}x{} connect {}. Supported sizes are 7x6, 8x7, 9x7, 6x5, 6x7, 7x7 and 5x4 connect 4, 4x4 connect 3 and 9x7 connect 5",
+                w, h, n
+            ),
+        }
+    };
+}
 
-fn main() {
+fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    let board = Board::new(args.show_threats);
-    let player1 = player_from_args(
-        Piece::Yellow,
-        &board,
-        &args.one_player,
-        &args.one_player_alg,
-        args.one_player_depth,
+    match &args.command {
+        None => match &args.load {
+            Some(path) => {
+                let record = GameRecord::load(path)?;
+                with_board_size!(
+                    record.width,
+                    record.height,
+                    record.connect,
+                    resume(&args, path, record)
+                )
+            }
+            None => with_board_size!(args.width, args.height, args.connect, play(&args)),
+        },
+        Some(Command::Tournament { players, games }) => {
+            with_board_size!(
+                args.width,
+                args.height,
+                args.connect,
+                tournament(players, *games)
+            )
+        }
+        Some(Command::Solve { moves, tt_size }) => {
+            with_board_size!(
+                args.width,
+                args.height,
+                args.connect,
+                solve(&args.board_file, moves, *tt_size)
+            )
+        }
+        Some(Command::Analyze {
+            moves,
+            alg,
+            depth,
+            iterations,
+            tt_size,
+        }) => {
+            let alg: Algorithm = alg.parse()?;
+            with_board_size!(
+                args.width,
+                args.height,
+                args.connect,
+                analyze(&args, moves, alg, *depth, *iterations, *tt_size)
+            )
+        }
+        Some(Command::Host {
+            port,
+            color,
+            player,
+        }) => with_board_size!(
+            args.width,
+            args.height,
+            args.connect,
+            host(&args, *port, color, player)
+        ),
+        Some(Command::Join { address, player }) => {
+            let (connection, setup) = network::join(address.as_str())?;
+            with_board_size!(
+                setup.width,
+                setup.height,
+                setup.connect,
+                join(&args, player, connection, setup)
+            )
+        }
+        Some(Command::Serve {
+            port,
+            bot,
+            analysis_depth,
+        }) => with_board_size!(
+            args.width,
+            args.height,
+            args.connect,
+            serve(&args, *port, bot, *analysis_depth)
+        ),
+        Some(Command::Engine {
+            alg,
+            depth,
+            iterations,
+            tt_size,
+        }) => {
+            let alg: Algorithm = alg.parse()?;
+            with_board_size!(
+                args.width,
+                args.height,
+                args.connect,
+                engine(&args, alg, *depth, *iterations, *tt_size)
+            )
+        }
+    }
+}
+
+fn play<const W: usize, const H: usize, const N: usize>(args: &Args) -> anyhow::Result<()> {
+    let board = start_board::<W, H, N>(args)?;
+
+    let record = GameRecord::new(
+        W,
+        H,
+        N,
+        PlayerConfig {
+            threads: args.threads,
+            iterations: args.one_player_iterations,
+            command: args.one_player_command.clone(),
+            ..PlayerConfig::new(
+                &args.one_player,
+                &args.one_player_alg,
+                args.one_player_depth,
+                args.one_player_move_time,
+                args.one_player_tt_size,
+            )
+        },
+        PlayerConfig {
+            threads: args.threads,
+            iterations: args.two_player_iterations,
+            command: args.two_player_command.clone(),
+            ..PlayerConfig::new(
+                &args.two_player,
+                &args.two_player_alg,
+                args.two_player_depth,
+                args.two_player_move_time,
+                args.two_player_tt_size,
+            )
+        },
+    );
+
+    run_game(board, record, args.save.clone(), args.tui)
+}
+
+/// The board a new game starts from, from `--board-file` and `--position`.
+fn start_board<const W: usize, const H: usize, const N: usize>(
+    args: &Args,
+) -> anyhow::Result<Board<W, H, N>> {
+    let mut board = match &args.board_file {
+        Some(path) => board_from_file(path)?,
+        None => Board::<W, H, N>::default(),
+    };
+    board.set_show_threats(args.show_threats);
+    if let Some(position) = &args.position {
+        board.play_moves(position)?;
+    }
+    if board.evaluate(false).is_terminal() {
+        bail!("The game is already over after {}", board.to_moves());
+    }
+
+    Ok(board)
+}
+
+fn resume<const W: usize, const H: usize, const N: usize>(
+    args: &Args,
+    path: &Path,
+    record: GameRecord,
+) -> anyhow::Result<()> {
+    let mut board = record.board::<W, H, N>()?;
+    board.set_show_threats(args.show_threats);
+    if record.result.is_some() || board.evaluate(false).is_terminal() {
+        bail!("The game in {} is already over", path.display());
+    }
+
+    // Carry on saving to the file the game came from unless told otherwise
+    let save = args.save.clone().or_else(|| Some(path.to_path_buf()));
+
+    run_game(board, record, save, args.tui)
+}
+
+fn run_game<const W: usize, const H: usize, const N: usize>(
+    board: Board<W, H, N>,
+    record: GameRecord,
+    save: Option<PathBuf>,
+    tui: bool,
+) -> anyhow::Result<()> {
+    let screen = Screen::shared(board.clone());
+
+    let (player1, player2, mut observers): (_, _, Vec<Box<dyn GameObserver<W, H, N>>>) = match tui {
+        true => (
+            tui::build_player(&record.player1, Piece::Yellow, &screen)?,
+            tui::build_player(&record.player2, Piece::Red, &screen)?,
+            vec![Box::new(TuiObserver::new(screen))],
+        ),
+        false => (
+            record.player1.build(Piece::Yellow)?,
+            record.player2.build(Piece::Red)?,
+            vec![Box::new(ConsoleObserver)],
+        ),
+    };
+    if let Some(path) = save {
+        observers.push(Box::new(SaveFile::new(path, record)));
+    }
+
+    let mut g = Game {
+        board,
+        player1,
+        player2,
+        observers,
+    };
+
+    if !tui {
+        g.run();
+        return Ok(());
+    }
+
+    let terminal = Terminal::enter()?;
+    let result = g.run();
+    terminal.wait_for_key()?;
+    drop(terminal);
+
+    // Leave the end of the game behind once the full screen is gone
+    match result.winner {
+        Some(winner) => println!("{:?} wins", winner),
+        None => println!("It's a draw"),
+    }
+    println!("{}", result.board);
+    println!("Moves: {}", result.board.to_moves());
+
+    Ok(())
+}
+
+fn tournament<const W: usize, const H: usize, const N: usize>(
+    players: &[PlayerConfig],
+    games: usize,
+) -> anyhow::Result<()> {
+    let mut tournament = Tournament::new(players.to_vec(), games)?;
+    let game_count = tournament.game_count();
+    let names: Vec<String> = tournament.players().iter().map(|p| p.to_string()).collect();
+
+    let mut played = 0;
+    tournament.play::<W, H, N>(|yellow, red, result| {
+        played += 1;
+
+        let outcome = match result.winner {
+            Some(Piece::Yellow) => format!("{} beat {}", names[yellow], names[red]),
+            Some(Piece::Red) => format!("{} beat {}", names[red], names[yellow]),
+            None => format!("{} drew with {}", names[yellow], names[red]),
+        };
+        println!(
+            "Game {}/{}: {} in {} moves ({} went first)",
+            played, game_count, outcome, result.plies, names[yellow]
+        );
+    })?;
+
+    println!();
+    print!("{}", tournament);
+
+    Ok(())
+}
+
+fn solve<const W: usize, const H: usize, const N: usize>(
+    board_file: &Option<PathBuf>,
+    moves: &str,
+    tt_size: usize,
+) -> anyhow::Result<()> {
+    let mut board = match board_file {
+        Some(path) => board_from_file(path)?,
+        None => Board::<W, H, N>::default(),
+    };
+    board.play_moves(moves)?;
+
+    let to_play = match board.min_or_maxing() {
+        MoM::Max => Piece::Yellow,
+        MoM::Min => Piece::Red,
+    };
+
+    println!("{}", board);
+    println!("{:?} to play", to_play);
+
+    let mut solver = Solver::new(tt_size * 1024 * 1024);
+
+    let start = Instant::now();
+    let solution = solver.solve(&mut board);
+    let elapsed = start.elapsed();
+
+    match solution.outcome {
+        Outcome::Win => println!("{:?} wins in {}", to_play, solution.winner_moves()),
+        Outcome::Loss => println!("{:?} wins in {}", to_play.other(), solution.winner_moves()),
+        Outcome::Draw => println!("Draw in {} plies", solution.plies),
+    }
+    println!("Searched {} positions in {:.2?}", solver.nodes(), elapsed);
+
+    Ok(())
+}
+
+fn analyze<const W: usize, const H: usize, const N: usize>(
+    args: &Args,
+    moves: &str,
+    alg: Algorithm,
+    depth: usize,
+    iterations: usize,
+    tt_size: usize,
+) -> anyhow::Result<()> {
+    let mut board = match &args.board_file {
+        Some(path) => board_from_file(path)?,
+        None => Board::<W, H, N>::default(),
+    };
+    board.play_moves(moves)?;
+    if board.evaluate(false).is_terminal() {
+        bail!("The game is already over after {}", board.to_moves());
+    }
+
+    let to_play = match board.min_or_maxing() {
+        MoM::Max => Piece::Yellow,
+        MoM::Min => Piece::Red,
+    };
+
+    println!("{}", board);
+    println!("{:?} to play", to_play);
+
+    let tree: Tree<Board<W, H, N>, BoardMove, BoardError> =
+        Tree::new(depth, alg, false, tt_size * 1024 * 1024)
+            .with_iterations(iterations)
+            .with_threads(args.threads);
+
+    let start = Instant::now();
+    let lines = tree.analyze(&mut board);
+    let elapsed = start.elapsed();
+
+    for (rank, (move_data, eval, pv)) in lines.iter().enumerate() {
+        println!(
+            "{:>2}. {}  {:>5}  pv: {}",
+            rank + 1,
+            move_data.column + 1,
+            eval.to_string(),
+            pv.iter()
+                .map(|m| m.column + 1)
+                .map(|c| c.to_string())
+                .collect::<String>()
+        );
+    }
+    println!(
+        "Evaluated {} positions in {:.2?}",
+        tree.eval_call_count(),
+        elapsed
     );
-    let player2 = player_from_args(
-        Piece::Red,
-        &board,
-        &args.two_player,
-        &args.two_player_alg,
-        args.two_player_depth,
+
+    Ok(())
+}
+
+fn engine<const W: usize, const H: usize, const N: usize>(
+    args: &Args,
+    alg: Algorithm,
+    depth: usize,
+    iterations: usize,
+    tt_size: usize,
+) -> anyhow::Result<()> {
+    let start = match &args.board_file {
+        Some(path) => board_from_file(path)?,
+        None => Board::<W, H, N>::default(),
+    };
+
+    let tree = Tree::new(depth, alg, false, tt_size * 1024 * 1024)
+        .with_iterations(iterations)
+        .with_threads(args.threads);
+
+    Engine::new(start, tree, depth, io::stdout()).run(io::stdin().lock())
+}
+
+fn host<const W: usize, const H: usize, const N: usize>(
+    args: &Args,
+    port: u16,
+    color: &str,
+    player: &PlayerConfig,
+) -> anyhow::Result<()> {
+    let board = start_board::<W, H, N>(args)?;
+    let color = match color {
+        "random" if rand::random() => Piece::Yellow,
+        "random" => Piece::Red,
+        color => parse_color(color)?,
+    };
+
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .with_context(|| format!("Couldn't listen on port {}", port))?;
+    println!(
+        "Waiting for another player on port {}",
+        listener.local_addr()?.port()
     );
 
+    let setup = Setup {
+        joiner: color.other(),
+        width: W,
+        height: H,
+        connect: N,
+        moves: board.to_moves(),
+    };
+    let connection = network::host(&listener, &setup)?;
+
+    play_remote(board, player, color, connection)
+}
+
+fn join<const W: usize, const H: usize, const N: usize>(
+    args: &Args,
+    player: &PlayerConfig,
+    connection: Connection,
+    setup: Setup,
+) -> anyhow::Result<()> {
+    let mut board = Board::<W, H, N>::from_moves(&setup.moves)?;
+    board.set_show_threats(args.show_threats);
+
+    play_remote(board, player, setup.joiner, connection)
+}
+
+/// Play `player` as `color` against whoever is on the other end of
+/// `connection`.
+fn play_remote<const W: usize, const H: usize, const N: usize>(
+    board: Board<W, H, N>,
+    player: &PlayerConfig,
+    color: Piece,
+    connection: Connection,
+) -> anyhow::Result<()> {
+    println!("Connected, you play {:?}", color);
+
+    let local = player.build(color)?;
+    let remote: Box<dyn Play<W, H, N>> =
+        Box::new(Remote::new(color.other(), connection, board.history()));
+    let (player1, player2) = match color {
+        Piece::Yellow => (local, remote),
+        Piece::Red => (remote, local),
+    };
+
     let mut g = Game {
         board,
         player1,
         player2,
+        observers: vec![Box::new(ConsoleObserver)],
     };
 
-    g.game_loop();
+    g.run();
+
+    Ok(())
+}
+
+fn serve<const W: usize, const H: usize, const N: usize>(
+    args: &Args,
+    port: u16,
+    bot: &PlayerConfig,
+    analysis_depth: usize,
+) -> anyhow::Result<()> {
+    let start = start_board::<W, H, N>(args)?;
+    let mut web_game = WebGame::new(start, bot.clone(), analysis_depth)?;
+
+    let server = tiny_http::Server::http(("127.0.0.1", port))
+        .map_err(|e| anyhow!("Couldn't listen on port {}: {}", port, e))?;
+    let port = server
+        .server_addr()
+        .to_ip()
+        .map(|address| address.port())
+        .unwrap_or(port);
+    println!("Serving on http://127.0.0.1:{}", port);
+
+    web_game.serve(&server);
+
+    Ok(())
+}
+
+fn board_from_file<const W: usize, const H: usize, const N: usize>(
+    path: &Path,
+) -> anyhow::Result<Board<W, H, N>> {
+    let picture = fs::read_to_string(path)
+        .with_context(|| format!("Couldn't read the board from {}", path.display()))?;
+
+    picture
+        .parse()
+        .with_context(|| format!("Couldn't read the board from {}", path.display()))
 }
 
 /// Play Connect 4 with us!
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// The type of player player1 will be
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The type of player player1 will be, human, bot, random or engine
     #[arg(short, long, default_value_t = String::from("human"))]
     one_player: String,
 
-    /// The alg for player1
+    /// The alg for player1, alphabeta, minimax or mcts, or for a human player1's hints
     #[arg(long, default_value_t = String::from("alphabeta"))]
     one_player_alg: String,
 
-    /// The depth for player1 3 is easy 8 is impossible
+    /// The depth for player1 3 is easy 8 is impossible, and how deep a human player1's hints look
     #[arg(long, default_value_t = 5)]
     one_player_depth: usize,
 
-    /// The type of player player2 will be
+    /// How many playouts player1 runs a move with the mcts alg, unless it has a move time
+    #[arg(long, default_value_t = mcts::DEFAULT_ITERATIONS)]
+    one_player_iterations: usize,
+
+    /// Search player1 deeper until this much time has passed (e.g. 500ms or 2s) instead of to a fixed depth
+    #[arg(long, value_parser = parse_duration)]
+    one_player_move_time: Option<Duration>,
+
+    /// How many MB player1's transposition table may use, 0 turns it off
+    #[arg(long, default_value_t = 16)]
+    one_player_tt_size: usize,
+
+    /// The program an engine player1 runs with its arguments, e.g. "./other-engine --fast"
+    #[arg(long)]
+    one_player_command: Option<String>,
+
+    /// The type of player player2 will be, human, bot, random or engine
     #[arg(short, long, default_value_t = String::from("bot"))]
     two_player: String,
 
-    /// The alg for player2
+    /// The alg for player2, alphabeta, minimax or mcts, or for a human player2's hints
     #[arg(long, default_value_t = String::from("alphabeta"))]
     two_player_alg: String,
 
-    /// The depth for player2 3 is easy 8 is impossible
+    /// The depth for player2 3 is easy 8 is impossible, and how deep a human player2's hints look
     #[arg(long, default_value_t = 5)]
     two_player_depth: usize,
 
+    /// How many playouts player2 runs a move with the mcts alg, unless it has a move time
+    #[arg(long, default_value_t = mcts::DEFAULT_ITERATIONS)]
+    two_player_iterations: usize,
+
+    /// Search player2 deeper until this much time has passed (e.g. 500ms or 2s) instead of to a fixed depth
+    #[arg(long, value_parser = parse_duration)]
+    two_player_move_time: Option<Duration>,
+
+    /// How many MB player2's transposition table may use, 0 turns it off
+    #[arg(long, default_value_t = 16)]
+    two_player_tt_size: usize,
+
+    /// The program an engine player2 runs with its arguments, e.g. "./other-engine --fast"
+    #[arg(long)]
+    two_player_command: Option<String>,
+
+    /// How many threads the bots search with, splitting the moves from the root between them
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+
     /// Show threats
     #[arg(long, default_value_t = false)]
     show_threats: bool,
+
+    /// Start from the position after these columns are played, 1 being the leftmost, e.g. 4453
+    #[arg(long)]
+    position: Option<String>,
+
+    /// Start from the board pictured in this file, one line per row with Y, R or _ per square.
+    /// A --position is played on from there
+    #[arg(long)]
+    board_file: Option<PathBuf>,
+
+    /// Play on a full screen in the terminal, choosing columns with the arrow keys
+    #[arg(long, default_value_t = false)]
+    tui: bool,
+
+    /// Save the game to this file after every move, as JSON
+    #[arg(long)]
+    save: Option<PathBuf>,
+
+    /// Resume the game saved in this file, with the players and board size it was saved with.
+    /// It carries on saving to the same file unless --save is given
+    #[arg(long, conflicts_with_all = ["position", "board_file"])]
+    load: Option<PathBuf>,
+
+    /// The number of columns on the board
+    #[arg(long, default_value_t = 7)]
+    width: usize,
+
+    /// The number of rows on the board
+    #[arg(long, default_value_t = 6)]
+    height: usize,
+
+    /// How many in a row it takes to win
+    #[arg(long, default_value_t = 4)]
+    connect: usize,
 }
 
-fn player_from_args(
-    color: Piece,
-    board: &Board,
-    player: &str,
-    alg: &str,
-    depth: usize,
-) -> Box<dyn Play> {
-    match player {
-        "human" => Box::new(Human {
-            name: color.to_string(),
-        }),
-        "bot" => {
-            let alg = match alg {
-                "alphabeta" => Algorithm::AlphaBeta,
-                "minimax" => Algorithm::MiniMax,
-                _ => panic!("Invalid alg type"),
-            };
-
-            Box::new(Bot::new(color, board.clone(), depth, alg, false))
-        }
-        "random" => Box::new(Random { color }),
-        _ => panic!("Invalid player type"),
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Play a round robin between bots and rate them
+    Tournament {
+        /// The players, e.g. random, bot, bot:depth=7,alg=minimax,threats or
+        /// engine:command=./other,move-time=100ms. Bot settings are alg, depth, iterations,
+        /// move-time, tt-size, threads and threats, engines take a command, depth and move-time
+        #[arg(required = true, num_args = 2..)]
+        players: Vec<PlayerConfig>,
+
+        /// How many games each pair of players plays, taking turns to go first
+        #[arg(long, default_value_t = 2)]
+        games: usize,
+    },
+
+    /// Work out who wins a position with perfect play
+    Solve {
+        /// The columns played so far, 1 being the leftmost, e.g. 4453
+        #[arg(default_value = "", hide_default_value = true)]
+        moves: String,
+
+        /// How many MB the solver's transposition table may use
+        #[arg(long, default_value_t = 64)]
+        tt_size: usize,
+    },
+
+    /// Rank every legal move in a position with the line of play expected after it
+    Analyze {
+        /// The columns played so far, 1 being the leftmost, e.g. 4453
+        #[arg(default_value = "", hide_default_value = true)]
+        moves: String,
+
+        /// The alg to search with, alphabeta, minimax or mcts
+        #[arg(long, default_value_t = String::from("alphabeta"))]
+        alg: String,
+
+        /// How deep to search after each move
+        #[arg(long, default_value_t = 7)]
+        depth: usize,
+
+        /// How many playouts to run with the mcts alg
+        #[arg(long, default_value_t = mcts::DEFAULT_ITERATIONS)]
+        iterations: usize,
+
+        /// How many MB the transposition table may use, 0 turns it off
+        #[arg(long, default_value_t = 16)]
+        tt_size: usize,
+    },
+
+    /// Wait for another player to join over the network and play them
+    ///
+    /// The board size, --position and --board-file set up the game for both players
+    Host {
+        /// The port to listen on, 0 picks a free one
+        #[arg(long, default_value_t = 4444)]
+        port: u16,
+
+        /// The colour to play, yellow (who goes first), red or random
+        #[arg(long, default_value_t = String::from("yellow"))]
+        color: String,
+
+        /// Who plays on this side, e.g. human, bot:depth=7 or engine:command=./other
+        #[arg(long, default_value = "human")]
+        player: PlayerConfig,
+    },
+
+    /// Join a game hosted with the host command, e.g. join 192.168.1.5:4444
+    Join {
+        /// The host's address and port
+        address: String,
+
+        /// Who plays on this side, e.g. human, bot:depth=7 or engine:command=./other
+        #[arg(long, default_value = "human")]
+        player: PlayerConfig,
+    },
+
+    /// Play in the browser, served on localhost with a JSON API behind the page
+    Serve {
+        /// The port to listen on, 0 picks a free one
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// The bot that moves when asked, e.g. bot:depth=7 or bot:alg=mcts,move-time=500ms
+        #[arg(long, default_value = "bot")]
+        bot: PlayerConfig,
+
+        /// How deep analysis searches after each move unless asked otherwise
+        #[arg(long, default_value_t = 7)]
+        analysis_depth: usize,
+    },
+
+    /// Answer a line based protocol on stdin and stdout so other programs can use the bot
+    Engine {
+        /// The alg to search with, alphabeta, minimax or mcts
+        #[arg(long, default_value_t = String::from("alphabeta"))]
+        alg: String,
+
+        /// How deep go searches when it isn't given a depth or a move time
+        #[arg(long, default_value_t = 7)]
+        depth: usize,
+
+        /// How many playouts to run with the mcts alg, unless go has a move time
+        #[arg(long, default_value_t = mcts::DEFAULT_ITERATIONS)]
+        iterations: usize,
+
+        /// How many MB the transposition table may use, 0 turns it off
+        #[arg(long, default_value_t = 16)]
+        tt_size: usize,
+    },
 }
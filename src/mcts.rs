@@ -0,0 +1,342 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::game::{GameBoard, GameEvaluation, MoM};
+
+/// How many playouts a search runs when it isn't given a time budget.
+pub const DEFAULT_ITERATIONS: usize = 20_000;
+
+/// How much UCT favours moves it has tried less often over moves that have
+/// done well.
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+#[derive(Debug)]
+struct Node<D> {
+    /// The move that led here, none for the root
+    move_data: Option<D>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Moves from here that don't have a node yet
+    untried: Vec<D>,
+    /// How the game ended, if it ended with this node's move
+    terminal: Option<GameEvaluation>,
+    /// Whether the player who made this node's move is the maximising one
+    maxing: bool,
+    visits: u32,
+    /// Points scored by the player who made this node's move, a win being
+    /// worth one and a draw a half
+    score: f64,
+}
+
+impl<D> Node<D> {
+    /// How the game looks after this node's move, as a win rate for the
+    /// maximising player scaled from -100 to 100. A finished game counts its
+    /// plies from before the move.
+    fn eval(&self) -> GameEvaluation {
+        if let Some(eval) = self.terminal {
+            return eval.add_plies(1);
+        }
+
+        let rate = self.score / self.visits.max(1) as f64;
+        let rate = if self.maxing { rate } else { 1.0 - rate };
+
+        GameEvaluation::OnGoing(((rate - 0.5) * 200.0).round() as isize)
+    }
+}
+
+/// A Monte Carlo tree search, grown one random playout at a time and choosing
+/// which line to play out with UCT.
+#[derive(Debug)]
+pub struct Mcts<D> {
+    nodes: Vec<Node<D>>,
+    eval_call_count: usize,
+}
+
+impl<D: Clone> Mcts<D> {
+    pub fn new<B, E>(board: &B) -> Self
+    where
+        B: GameBoard<D, E>,
+    {
+        let eval = board.evaluate(false);
+        let root = Node {
+            move_data: None,
+            parent: None,
+            children: vec![],
+            untried: if eval.is_terminal() {
+                vec![]
+            } else {
+                board.list_moves()
+            },
+            terminal: eval.is_terminal().then_some(eval),
+            maxing: matches!(board.min_or_maxing(), MoM::Min),
+            visits: 0,
+            score: 0.0,
+        };
+
+        Self {
+            nodes: vec![root],
+            eval_call_count: 1,
+        }
+    }
+
+    /// Run `iterations` playouts from `board`, the position the search was
+    /// created for, stopping early once `stop` says so.
+    pub fn run<B, E>(
+        &mut self,
+        board: &B,
+        iterations: usize,
+        mut stop: impl FnMut() -> bool,
+        rng: &mut impl Rng,
+    ) where
+        B: GameBoard<D, E> + Clone,
+        E: std::fmt::Debug,
+    {
+        for i in 0..iterations {
+            // Reading the clock every playout would slow them down
+            if i % 64 == 0 && stop() {
+                break;
+            }
+
+            self.iterate(&mut board.clone(), rng);
+        }
+    }
+
+    fn iterate<B, E>(&mut self, board: &mut B, rng: &mut impl Rng)
+    where
+        B: GameBoard<D, E>,
+        E: std::fmt::Debug,
+    {
+        let mut node = 0;
+
+        while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+            node = self.select(node);
+            board
+                .apply_move(self.nodes[node].move_data.as_ref().unwrap())
+                .unwrap();
+        }
+
+        if !self.nodes[node].untried.is_empty() {
+            node = self.expand(node, board, rng);
+        }
+
+        let result = match self.nodes[node].terminal {
+            Some(eval) => eval,
+            None => self.playout(board, rng),
+        };
+
+        let points = match result {
+            GameEvaluation::Win(_) => 1.0,
+            GameEvaluation::Lose(_) => 0.0,
+            _ => 0.5,
+        };
+
+        let mut current = Some(node);
+        while let Some(index) = current {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.score += if node.maxing { points } else { 1.0 - points };
+            current = node.parent;
+        }
+    }
+
+    /// The child of `node` with the highest upper confidence bound.
+    fn select(&self, node: usize) -> usize {
+        let log_visits = (self.nodes[node].visits as f64).ln();
+
+        let uct = |child: usize| {
+            let child = &self.nodes[child];
+            let visits = child.visits as f64;
+            child.score / visits + EXPLORATION * (log_visits / visits).sqrt()
+        };
+
+        *self.nodes[node]
+            .children
+            .iter()
+            .max_by(|&&a, &&b| uct(a).total_cmp(&uct(b)))
+            .unwrap()
+    }
+
+    /// Play one of the untried moves from `node` and give it a node.
+    fn expand<B, E>(&mut self, node: usize, board: &mut B, rng: &mut impl Rng) -> usize
+    where
+        B: GameBoard<D, E>,
+        E: std::fmt::Debug,
+    {
+        let untried = &mut self.nodes[node].untried;
+        let move_data = untried.swap_remove(rng.gen_range(0..untried.len()));
+
+        let maxing = matches!(board.min_or_maxing(), MoM::Max);
+        board.apply_move(&move_data).unwrap();
+        let eval = self.evaluate(board);
+
+        let child = self.nodes.len();
+        self.nodes.push(Node {
+            move_data: Some(move_data),
+            parent: Some(node),
+            children: vec![],
+            untried: if eval.is_terminal() {
+                vec![]
+            } else {
+                board.list_moves()
+            },
+            terminal: eval.is_terminal().then_some(eval),
+            maxing,
+            visits: 0,
+            score: 0.0,
+        });
+        self.nodes[node].children.push(child);
+
+        child
+    }
+
+    /// Play random moves until the game ends.
+    fn playout<B, E>(&mut self, board: &mut B, rng: &mut impl Rng) -> GameEvaluation
+    where
+        B: GameBoard<D, E>,
+        E: std::fmt::Debug,
+    {
+        loop {
+            let move_data = board.list_moves().choose(rng).unwrap().clone();
+            board.apply_move(&move_data).unwrap();
+
+            let eval = self.evaluate(board);
+            if eval.is_terminal() {
+                return eval;
+            }
+        }
+    }
+
+    fn evaluate<B, E>(&mut self, board: &B) -> GameEvaluation
+    where
+        B: GameBoard<D, E>,
+    {
+        self.eval_call_count += 1;
+        board.evaluate(false)
+    }
+
+    /// The root's moves with how they did and the line of play expected
+    /// after each, most played first.
+    pub fn ranked_lines(&self) -> Vec<(D, GameEvaluation, Vec<D>)> {
+        let mut children = self.nodes[0].children.clone();
+        children.sort_by_key(|&child| std::cmp::Reverse(self.nodes[child].visits));
+
+        children
+            .into_iter()
+            .map(|child| {
+                let node = &self.nodes[child];
+                (
+                    node.move_data.clone().unwrap(),
+                    node.eval(),
+                    self.line(child),
+                )
+            })
+            .collect()
+    }
+
+    /// The line of play the search expects, following the most played move
+    /// from each position.
+    pub fn pv(&self) -> Vec<D> {
+        self.line(0)
+    }
+
+    /// The move to `node`, if it has one, and the most played moves after it.
+    fn line(&self, mut node: usize) -> Vec<D> {
+        let mut line: Vec<D> = self.nodes[node].move_data.iter().cloned().collect();
+
+        while let Some(&child) = self.nodes[node]
+            .children
+            .iter()
+            .max_by_key(|&&child| self.nodes[child].visits)
+        {
+            line.push(self.nodes[child].move_data.clone().unwrap());
+            node = child;
+        }
+
+        line
+    }
+
+    /// How the most played move from the root did.
+    pub fn eval(&self) -> GameEvaluation {
+        self.nodes[0]
+            .children
+            .iter()
+            .max_by_key(|&&child| self.nodes[child].visits)
+            .map_or(GameEvaluation::Draw, |&child| self.nodes[child].eval())
+    }
+
+    /// How many playouts have been run.
+    pub fn iterations(&self) -> u32 {
+        self.nodes[0].visits
+    }
+
+    /// How many times `evaluate` has been called.
+    pub fn eval_call_count(&self) -> usize {
+        self.eval_call_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{
+        board::{board::Board, board_move::BoardMove},
+        game::MovePiece,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[0, 0, 1, 1, 2, 2], 3)]
+    #[case(&[0, 6, 1, 6, 2], 3)]
+    #[case(&[3, 0, 3, 0, 3], 3)]
+    fn test_finds_win_or_block(#[case] moves: &[usize], #[case] expected_column: usize) {
+        let board = <Board>::from_columns(moves);
+        let mut mcts: Mcts<BoardMove> = Mcts::new(&board);
+
+        mcts.run(&board, 5_000, || false, &mut StdRng::seed_from_u64(7));
+
+        assert_eq!(mcts.pv()[0].column, expected_column);
+        assert_eq!(mcts.ranked_lines()[0].0.column, expected_column);
+        assert_eq!(mcts.iterations(), 5_000);
+    }
+
+    #[test]
+    fn test_eval() {
+        let board = <Board>::from_columns(&[0, 0, 1, 1, 2, 2]);
+        let mut mcts: Mcts<BoardMove> = Mcts::new(&board);
+
+        mcts.run(&board, 1_000, || false, &mut StdRng::seed_from_u64(7));
+
+        assert_eq!(mcts.eval(), GameEvaluation::Win(1));
+        assert_eq!(mcts.ranked_lines().len(), 7);
+    }
+
+    #[test]
+    fn test_deadline() {
+        let board = <Board>::from_columns(&[]);
+        let mut mcts: Mcts<BoardMove> = Mcts::new(&board);
+
+        let deadline = Instant::now() + Duration::from_millis(50);
+        mcts.run(
+            &board,
+            usize::MAX,
+            || Instant::now() >= deadline,
+            &mut StdRng::seed_from_u64(7),
+        );
+
+        assert!(mcts.iterations() > 0);
+        assert!(board.is_move_valid(&mcts.pv()[0]));
+    }
+
+    #[test]
+    fn test_game_over() {
+        let board = <Board>::from_columns(&[0, 1, 0, 1, 0, 1, 0]);
+        let mut mcts: Mcts<BoardMove> = Mcts::new(&board);
+
+        mcts.run(&board, 10, || false, &mut StdRng::seed_from_u64(7));
+
+        assert!(mcts.pv().is_empty());
+        assert!(mcts.ranked_lines().is_empty());
+    }
+}
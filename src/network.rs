@@ -0,0 +1,221 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::board::piece::Piece;
+
+/// The version of the protocol, so players on builds that don't speak the same
+/// one find out before they play.
+pub const VERSION: u32 = 1;
+
+/// How long either side waits for the other during the handshake. Once the
+/// game starts a player may think as long as they like.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A line based connection to the other player.
+#[derive(Debug)]
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    pub fn send(&mut self, line: &str) -> anyhow::Result<()> {
+        writeln!(self.writer, "{}", line)
+            .map_err(|e| anyhow!("Lost the connection to the other player: {}", e))
+    }
+
+    /// The next line from the other player, or an error if they've gone.
+    pub fn receive(&mut self) -> anyhow::Result<String> {
+        let mut line = String::new();
+
+        match self.reader.read_line(&mut line) {
+            Ok(0) => bail!("The other player disconnected"),
+            Ok(_) => Ok(line.trim().to_string()),
+            Err(e) => bail!("Lost the connection to the other player: {}", e),
+        }
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.writer.set_read_timeout(timeout)
+    }
+}
+
+/// What the players agree on before the game starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setup {
+    /// The colour of the player who joined, the host plays the other
+    pub joiner: Piece,
+    pub width: usize,
+    pub height: usize,
+    pub connect: usize,
+    /// The moves already played, in the same notation as `--position`
+    pub moves: String,
+}
+
+impl Setup {
+    fn to_line(&self) -> String {
+        format!(
+            "start {} {} {} {} {}",
+            self.joiner.name(),
+            self.width,
+            self.height,
+            self.connect,
+            self.moves
+        )
+        .trim_end()
+        .to_string()
+    }
+
+    fn from_line(line: &str) -> anyhow::Result<Self> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let [start, joiner, width, height, connect, rest @ ..] = words.as_slice() else {
+            bail!("Expected the game to start, got {}", line);
+        };
+        if *start != "start" || rest.len() > 1 {
+            bail!("Expected the game to start, got {}", line);
+        }
+
+        Ok(Self {
+            joiner: parse_color(joiner)?,
+            width: width.parse()?,
+            height: height.parse()?,
+            connect: connect.parse()?,
+            moves: rest.first().unwrap_or(&"").to_string(),
+        })
+    }
+}
+
+/// Read `yellow` or `red`.
+pub fn parse_color(s: &str) -> anyhow::Result<Piece> {
+    match s {
+        "yellow" => Ok(Piece::Yellow),
+        "red" => Ok(Piece::Red),
+        s => bail!("Invalid colour {}, use yellow or red", s),
+    }
+}
+
+/// Wait for another player to join and tell them how the game is set up.
+///
+/// The joiner says `hello` and the protocol version, the host answers with
+/// `start` and the setup, and the joiner says `ready`.
+pub fn host(listener: &TcpListener, setup: &Setup) -> anyhow::Result<Connection> {
+    let (stream, _) = listener.accept()?;
+    let mut connection = Connection::new(stream)?;
+    connection.set_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+    let hello = connection.receive()?;
+    match hello.strip_prefix("hello ").map(str::parse::<u32>) {
+        Some(Ok(VERSION)) => {}
+        Some(Ok(version)) => {
+            let reason = format!("The host speaks version {}, not {}", VERSION, version);
+            let _ = connection.send(&format!("error {}", reason));
+            bail!(reason);
+        }
+        _ => bail!("Expected hello from the other player, got {}", hello),
+    }
+
+    connection.send(&setup.to_line())?;
+
+    let ready = connection.receive()?;
+    if ready != "ready" {
+        bail!("Expected the other player to be ready, got {}", ready);
+    }
+
+    connection.set_timeout(None)?;
+    Ok(connection)
+}
+
+/// Join the game hosted at `address` and find out how it's set up.
+pub fn join(address: impl ToSocketAddrs) -> anyhow::Result<(Connection, Setup)> {
+    let stream = TcpStream::connect(address).context("Couldn't reach the host")?;
+    let mut connection = Connection::new(stream)?;
+    connection.set_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+    connection.send(&format!("hello {}", VERSION))?;
+
+    let start = connection.receive()?;
+    if let Some(reason) = start.strip_prefix("error ") {
+        bail!("The host refused: {}", reason);
+    }
+    let setup = Setup::from_line(&start)?;
+
+    connection.send("ready")?;
+
+    connection.set_timeout(None)?;
+    Ok((connection, setup))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Setup { joiner: Piece::Red, width: 7, height: 6, connect: 4, moves: "".into() })]
+    #[case(Setup { joiner: Piece::Yellow, width: 9, height: 7, connect: 5, moves: "4453".into() })]
+    fn test_handshake(#[case] setup: Setup) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let joiner = thread::spawn(move || join(address).unwrap().1);
+        host(&listener, &setup).unwrap();
+
+        assert_eq!(joiner.join().unwrap(), setup);
+    }
+
+    #[rstest]
+    #[case("hello 2", "error The host speaks version 1, not 2")]
+    #[case("hi", "")]
+    fn test_handshake_refused(#[case] hello: &'static str, #[case] reply: &'static str) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let joiner = thread::spawn(move || {
+            let mut connection = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+            connection.send(hello).unwrap();
+            connection.receive().unwrap_or_default()
+        });
+        let setup = Setup {
+            joiner: Piece::Red,
+            width: 7,
+            height: 6,
+            connect: 4,
+            moves: "".into(),
+        };
+
+        assert!(host(&listener, &setup).is_err());
+        assert_eq!(joiner.join().unwrap(), reply);
+    }
+
+    #[rstest]
+    #[case("start red 7 6 4")]
+    #[case("start yellow 7 6 4 4453")]
+    fn test_setup_round_trip(#[case] line: &str) {
+        assert_eq!(Setup::from_line(line).unwrap().to_line(), line);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("begin red 7 6 4")]
+    #[case("start green 7 6 4")]
+    #[case("start red 7 6")]
+    #[case("start red 7 6 4 44 53")]
+    fn test_setup_invalid(#[case] line: &str) {
+        assert!(Setup::from_line(line).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    board::{
+        board::{Board, BoardError},
+        board_move::BoardMove,
+        piece::Piece,
+    },
+    game::{Ending, GameEvaluation, GameResult, MoveRecord, Play},
+};
+
+/// Gets told what happens in a [`Game`](crate::game::Game) as it happens.
+///
+/// Every event does nothing by default so observers only handle what they
+/// care about.
+pub trait GameObserver<const W: usize = 7, const H: usize = 6, const N: usize = 4> {
+    /// The game is about to start from `board`, which may already have moves
+    /// on it.
+    fn on_game_start(
+        &mut self,
+        _board: &Board<W, H, N>,
+        _player1: &dyn Play<W, H, N>,
+        _player2: &dyn Play<W, H, N>,
+    ) {
+    }
+
+    /// `player` is about to be asked for a move.
+    fn on_move_requested(&mut self, _board: &Board<W, H, N>, _player: &dyn Play<W, H, N>) {}
+
+    /// `player` played `record`, `board` is the board after it.
+    fn on_move_played(
+        &mut self,
+        _board: &Board<W, H, N>,
+        _player: &dyn Play<W, H, N>,
+        _record: &MoveRecord,
+    ) {
+    }
+
+    /// The board was evaluated after a move to see if the game is over.
+    fn on_evaluation(&mut self, _board: &Board<W, H, N>, _eval: GameEvaluation) {}
+
+    /// A player asked to take back the last round.
+    fn on_undo(&mut self, _board: &Board<W, H, N>, _undone: &Result<(), BoardError>) {}
+
+    /// `player` tried a move the board didn't allow, which ends the game.
+    fn on_illegal_move(
+        &mut self,
+        _board: &Board<W, H, N>,
+        _player: &dyn Play<W, H, N>,
+        _move_data: &BoardMove,
+        _error: &BoardError,
+    ) {
+    }
+
+    /// `player` gave up for `reason`, which ends the game.
+    fn on_forfeit(&mut self, _board: &Board<W, H, N>, _player: &dyn Play<W, H, N>, _reason: &str) {}
+
+    fn on_game_over(
+        &mut self,
+        _result: &GameResult<W, H, N>,
+        _player1: &dyn Play<W, H, N>,
+        _player2: &dyn Play<W, H, N>,
+    ) {
+    }
+}
+
+/// Prints the game to the console for people playing it.
+#[derive(Debug, Default)]
+pub struct ConsoleObserver;
+
+impl<const W: usize, const H: usize, const N: usize> GameObserver<W, H, N> for ConsoleObserver {
+    fn on_move_requested(&mut self, board: &Board<W, H, N>, player: &dyn Play<W, H, N>) {
+        if !player.needs_to_see_board() {
+            return;
+        }
+
+        let mut board = board.clone();
+        board.calculate_threats();
+        println!("{}", board);
+        println!("Please enter your move, hint or undo:");
+    }
+
+    fn on_move_played(
+        &mut self,
+        _board: &Board<W, H, N>,
+        player: &dyn Play<W, H, N>,
+        record: &MoveRecord,
+    ) {
+        if let Some(report) = &record.search {
+            println!(
+                "Searched to depth {} and evaluated {} times with {:?}",
+                report.depth, report.nodes, report.alg
+            );
+            println!("eval {} pv: {}", report.eval, format_pv(&report.pv));
+            if let Some((hits, misses)) = report.tt_stats {
+                println!("Transposition table: {} hits, {} misses", hits, misses);
+            }
+
+            if let Some((winner, moves)) = report.eval_after_move().forced_win() {
+                if moves > 0 {
+                    println!("{:?} wins in {}", winner, moves);
+                }
+            }
+        }
+
+        if player.should_announce_move() {
+            println!("Played {}", record.move_data);
+        }
+
+        println!();
+        println!();
+    }
+
+    fn on_evaluation(&mut self, _board: &Board<W, H, N>, eval: GameEvaluation) {
+        println!("Evaluating current board state");
+        if let GameEvaluation::OnGoing(val) = eval {
+            println!("The game continues with the eval {}.", val);
+            println!();
+        }
+    }
+
+    fn on_undo(&mut self, _board: &Board<W, H, N>, undone: &Result<(), BoardError>) {
+        match undone {
+            Ok(()) => println!("Took back the last round"),
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    fn on_illegal_move(
+        &mut self,
+        board: &Board<W, H, N>,
+        player: &dyn Play<W, H, N>,
+        move_data: &BoardMove,
+        error: &BoardError,
+    ) {
+        println!(
+            "Move {:?} for player {} failed becuase: {:?}.",
+            move_data, player, error
+        );
+        println!("Exiting");
+        println!("{}", board);
+    }
+
+    fn on_forfeit(&mut self, board: &Board<W, H, N>, player: &dyn Play<W, H, N>, reason: &str) {
+        println!("{} forfeits: {}", player, reason);
+        println!("{}", board);
+    }
+
+    fn on_game_over(
+        &mut self,
+        result: &GameResult<W, H, N>,
+        player1: &dyn Play<W, H, N>,
+        player2: &dyn Play<W, H, N>,
+    ) {
+        // The illegal move or forfeit has already been reported
+        if matches!(result.ending, Ending::IllegalMove | Ending::Forfeit) {
+            return;
+        }
+
+        match result.winner {
+            Some(Piece::Yellow) => print_boardered(&format!("{} beat {}!", player1, player2)),
+            Some(Piece::Red) => print_boardered(&format!("{} beat {}!", player2, player1)),
+            None => print_boardered("It's a Draw!"),
+        }
+        println!("{}", result.board);
+        println!("Moves: {}", result.board.to_moves());
+    }
+}
+
+/// A line of play as the columns to type, separated by spaces.
+pub fn format_pv(pv: &[BoardMove]) -> String {
+    pv.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn print_boardered(s: &str) {
+    let border = "*".repeat(20);
+    println!();
+    println!("{}", border);
+    println!("{}", s);
+    println!("{}", border);
+    println!();
+}
@@ -1,4 +1,7 @@
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    time::Duration,
+};
 
 use crate::{
     board::{
@@ -6,40 +9,56 @@ use crate::{
         board_move::BoardMove,
         piece::Piece,
     },
-    game::{MovePiece, Play},
-    tree::{Algorithm, Tree},
+    game::Play,
+    tree::{Algorithm, SearchReport, Tree},
 };
 
 #[derive(Debug)]
-pub struct Bot {
+pub struct Bot<const W: usize = 7, const H: usize = 6, const N: usize = 4> {
     pub color: Piece,
-    game_tree: Tree<Board, BoardMove, BoardError>,
+    game_tree: Tree<Board<W, H, N>, BoardMove, BoardError>,
+    move_time: Option<Duration>,
 }
 
-impl Display for Bot {
+impl<const W: usize, const H: usize, const N: usize> Display for Bot<W, H, N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.color)
     }
 }
 
-impl Bot {
+impl<const W: usize, const H: usize, const N: usize> Bot<W, H, N> {
     pub fn new(
         color: Piece,
-        board: Board,
         depth: usize,
         alg: Algorithm,
         use_threats: bool,
+        move_time: Option<Duration>,
+        tt_size: usize,
+        threads: usize,
     ) -> Self {
-        let mut game_tree = Tree::new(board.clone(), depth, alg, use_threats);
-        game_tree.walk_start(board);
+        let game_tree = Tree::new(depth, alg, use_threats, tt_size).with_threads(threads);
+
+        Self {
+            color,
+            game_tree,
+            move_time,
+        }
+    }
 
-        Self { color, game_tree }
+    /// How many playouts a Monte Carlo bot runs a move when it has no move
+    /// time.
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.game_tree = self.game_tree.with_iterations(iterations);
+        self
     }
 }
 
-impl Play for Bot {
-    fn get_move(&mut self, mut board: Board) -> BoardMove {
-        self.game_tree.walk_start(board.clone());
+impl<const W: usize, const H: usize, const N: usize> Play<W, H, N> for Bot<W, H, N> {
+    fn get_move(&mut self, mut board: Board<W, H, N>) -> BoardMove {
+        if let Some(move_time) = self.move_time {
+            return self.game_tree.get_best_move_timed(&mut board, move_time);
+        }
+
         self.game_tree.get_best_move(&mut board)
     }
 
@@ -50,4 +69,8 @@ impl Play for Bot {
     fn should_announce_move(&self) -> bool {
         true
     }
+
+    fn search_report(&self) -> Option<SearchReport<BoardMove>> {
+        self.game_tree.last_report()
+    }
 }
@@ -0,0 +1,286 @@
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::piece::Piece,
+    game::Play,
+    mcts,
+    player::{bot::Bot, external::External, human::Human, random::Random},
+    tree::Algorithm,
+};
+
+/// Everything needed to build a player again, e.g. when a saved game is
+/// resumed.
+///
+/// It can also be written as a spec, the kind of player optionally followed by
+/// a colon and comma separated settings, e.g. `bot:depth=7,alg=minimax,threats`.
+/// The settings are `alg`, `depth`, `iterations`, `move-time`, `tt-size`,
+/// `threads`, `threats` and, for engines, `command`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerConfig {
+    /// `human`, `bot`, `random` or `engine`
+    pub kind: String,
+    /// `alphabeta`, `minimax` or `mcts`, used by bots and for humans' hints
+    pub alg: String,
+    pub depth: usize,
+    /// How many playouts `mcts` runs a move without a move time
+    #[serde(default = "default_iterations")]
+    pub iterations: usize,
+    /// Search deeper until this many milliseconds have passed instead of to a
+    /// fixed depth
+    pub move_time_ms: Option<u64>,
+    /// How many MB the transposition table may use
+    pub tt_size: usize,
+    /// Whether bots count threats in their evaluation
+    #[serde(default)]
+    pub use_threats: bool,
+    /// How many threads bots search with
+    #[serde(default = "one_thread")]
+    pub threads: usize,
+    /// The program an `engine` player runs, with its arguments
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+fn one_thread() -> usize {
+    1
+}
+
+fn default_iterations() -> usize {
+    mcts::DEFAULT_ITERATIONS
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self::new("bot", "alphabeta", 5, None, 16)
+    }
+}
+
+impl PlayerConfig {
+    pub fn new(
+        kind: &str,
+        alg: &str,
+        depth: usize,
+        move_time: Option<Duration>,
+        tt_size: usize,
+    ) -> Self {
+        Self {
+            kind: kind.to_string(),
+            alg: alg.to_string(),
+            depth,
+            iterations: mcts::DEFAULT_ITERATIONS,
+            move_time_ms: move_time.map(|t| t.as_millis() as u64),
+            tt_size,
+            use_threats: false,
+            threads: 1,
+            command: None,
+        }
+    }
+
+    pub fn build<const W: usize, const H: usize, const N: usize>(
+        &self,
+        color: Piece,
+    ) -> anyhow::Result<Box<dyn Play<W, H, N>>> {
+        let alg: Algorithm = self.alg.parse()?;
+
+        let player: Box<dyn Play<W, H, N>> = match self.kind.as_str() {
+            "human" => Box::new(Human::new(
+                color.to_string(),
+                self.depth,
+                alg,
+                self.tt_size * 1024 * 1024,
+            )),
+            "bot" => Box::new(
+                Bot::new(
+                    color,
+                    self.depth,
+                    alg,
+                    self.use_threats,
+                    self.move_time_ms.map(Duration::from_millis),
+                    self.tt_size * 1024 * 1024,
+                    self.threads,
+                )
+                .with_iterations(self.iterations),
+            ),
+            "random" => Box::new(Random { color }),
+            "engine" => {
+                let Some(command) = &self.command else {
+                    bail!("An engine player needs a command to run");
+                };
+                Box::new(External::new(
+                    color,
+                    command,
+                    self.depth,
+                    self.move_time_ms.map(Duration::from_millis),
+                )?)
+            }
+            kind => bail!("Invalid player type {}", kind),
+        };
+
+        Ok(player)
+    }
+}
+
+impl FromStr for PlayerConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, settings) = s.split_once(':').unwrap_or((s, ""));
+
+        let mut config = Self {
+            kind: kind.trim().to_string(),
+            ..Default::default()
+        };
+
+        for setting in settings.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting.split_once('=').unwrap_or((setting, ""));
+
+            match key {
+                "alg" => config.alg = value.to_string(),
+                "depth" => config.depth = value.parse()?,
+                "iterations" => config.iterations = value.parse()?,
+                "move-time" => {
+                    let move_time = parse_duration(value).map_err(|e| anyhow!(e))?;
+                    config.move_time_ms = Some(move_time.as_millis() as u64);
+                }
+                "tt-size" => config.tt_size = value.parse()?,
+                "threads" => config.threads = value.parse()?,
+                "threats" => config.use_threats = value.is_empty() || value.parse()?,
+                "command" => config.command = Some(value.to_string()),
+                _ => bail!("Unknown player setting {} in {}", key, s),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Writes the config as a spec, leaving out the settings that are defaults.
+impl Display for PlayerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let default = Self::default();
+        let mut settings = vec![];
+
+        if self.alg != default.alg {
+            settings.push(format!("alg={}", self.alg));
+        }
+        if self.depth != default.depth && self.move_time_ms.is_none() {
+            settings.push(format!("depth={}", self.depth));
+        }
+        if self.iterations != default.iterations && self.move_time_ms.is_none() {
+            settings.push(format!("iterations={}", self.iterations));
+        }
+        if let Some(move_time) = self.move_time_ms {
+            settings.push(format!("move-time={}ms", move_time));
+        }
+        if self.tt_size != default.tt_size {
+            settings.push(format!("tt-size={}", self.tt_size));
+        }
+        if self.threads != default.threads {
+            settings.push(format!("threads={}", self.threads));
+        }
+        if self.use_threats {
+            settings.push("threats".to_string());
+        }
+        if let Some(command) = &self.command {
+            settings.push(format!("command={}", command));
+        }
+
+        write!(f, "{}", self.kind)?;
+        if matches!(self.kind.as_str(), "bot" | "engine") && !settings.is_empty() {
+            write!(f, ":{}", settings.join(","))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Read a duration like `500ms`, `2s` or `1.5m`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (value, unit) = match s.find(|c: char| c.is_alphabetic()) {
+        Some(i) => s.split_at(i),
+        None => return Err(format!("{s} needs a unit: ms, s or m")),
+    };
+
+    let value: f64 = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("{value} isn't a number"))?;
+
+    let seconds = match unit {
+        "ms" => value / 1000.0,
+        "s" => value,
+        "m" => value * 60.0,
+        _ => return Err(format!("{unit} isn't a unit: use ms, s or m")),
+    };
+
+    Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("random", PlayerConfig { kind: "random".into(), ..Default::default() })]
+    #[case("bot", PlayerConfig::default())]
+    #[case("bot:depth=7", PlayerConfig { depth: 7, ..Default::default() })]
+    #[case(
+        "bot:alg=minimax, threats",
+        PlayerConfig { alg: "minimax".into(), use_threats: true, ..Default::default() }
+    )]
+    #[case(
+        "bot:move-time=1.5s,tt-size=0,threats=false",
+        PlayerConfig { move_time_ms: Some(1500), tt_size: 0, ..Default::default() }
+    )]
+    #[case("bot:threads=8", PlayerConfig { threads: 8, ..Default::default() })]
+    #[case(
+        "bot:alg=mcts,iterations=500",
+        PlayerConfig { alg: "mcts".into(), iterations: 500, ..Default::default() }
+    )]
+    #[case(
+        "engine:command=./other --fast,move-time=100ms",
+        PlayerConfig {
+            kind: "engine".into(),
+            command: Some("./other --fast".into()),
+            move_time_ms: Some(100),
+            ..Default::default()
+        }
+    )]
+    fn test_from_str(#[case] spec: &str, #[case] expected: PlayerConfig) {
+        assert_eq!(spec.parse::<PlayerConfig>().unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("bot:depth")]
+    #[case("bot:depth=deep")]
+    #[case("bot:move-time=5")]
+    #[case("bot:colour=red")]
+    fn test_from_str_error(#[case] spec: &str) {
+        assert!(spec.parse::<PlayerConfig>().is_err());
+    }
+
+    #[rstest]
+    #[case("random")]
+    #[case("bot")]
+    #[case("bot:depth=7")]
+    #[case("bot:alg=minimax,depth=3,tt-size=0,threats")]
+    #[case("bot:move-time=250ms")]
+    #[case("bot:depth=9,threads=4")]
+    #[case("bot:alg=mcts,iterations=500")]
+    #[case("engine:depth=8,command=./other --fast")]
+    fn test_display(#[case] spec: &str) {
+        assert_eq!(spec.parse::<PlayerConfig>().unwrap().to_string(), spec);
+    }
+
+    #[rstest]
+    #[case("500ms", Duration::from_millis(500))]
+    #[case("2s", Duration::from_secs(2))]
+    #[case("1.5m", Duration::from_secs(90))]
+    fn test_parse_duration(#[case] s: &str, #[case] expected: Duration) {
+        assert_eq!(parse_duration(s), Ok(expected));
+    }
+}
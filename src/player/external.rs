@@ -0,0 +1,303 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::{
+    board::{board::Board, board_move::BoardMove, piece::Piece},
+    game::{Play, PlayerAction},
+};
+
+/// How much longer than its move time an engine gets to reply, for writing
+/// the reply and the odd slow move.
+const GRACE: Duration = Duration::from_secs(1);
+
+/// How long an engine searching to a depth gets to reply.
+pub const DEPTH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long an engine gets to say it's ready after starting.
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Another program playing through the engine protocol, the one
+/// `quatre_con engine` speaks.
+///
+/// Each turn it's sent the moves played so far and asked for a move. It
+/// forfeits if it exits, takes too long or replies with something that isn't
+/// a column, and a column the board doesn't allow is an illegal move.
+#[derive(Debug)]
+pub struct External {
+    pub color: Piece,
+    child: Child,
+    stdin: ChildStdin,
+    /// The engine's output a line at a time, read on its own thread so
+    /// waiting for a reply can time out
+    lines: Receiver<String>,
+    /// The `go` command sent each turn
+    go: String,
+    timeout: Duration,
+}
+
+impl Display for External {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.color)
+    }
+}
+
+impl External {
+    /// Start `command`, the program and its arguments separated by spaces,
+    /// and wait for it to be ready.
+    ///
+    /// With a `move_time` it gets that long a move, otherwise it searches to
+    /// `depth` and gets `DEPTH_TIMEOUT`.
+    pub fn new(
+        color: Piece,
+        command: &str,
+        depth: usize,
+        move_time: Option<Duration>,
+    ) -> anyhow::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next().context("The engine command is empty")?;
+
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Couldn't start the engine {}", command))?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let (go, timeout) = match move_time {
+            Some(move_time) => (
+                format!("go movetime {}", move_time.as_millis()),
+                move_time + GRACE,
+            ),
+            None => (format!("go depth {}", depth), DEPTH_TIMEOUT),
+        };
+
+        let mut external = Self {
+            color,
+            child,
+            stdin,
+            lines,
+            go,
+            timeout,
+        };
+
+        external.send("isready")?;
+        external
+            .wait_for(READY_TIMEOUT, |line| (line == "readyok").then_some(()))
+            .with_context(|| format!("The engine {} didn't say it was ready", command))?;
+
+        Ok(external)
+    }
+
+    fn send(&mut self, line: &str) -> anyhow::Result<()> {
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| self.exited())
+    }
+
+    /// Read lines until `reply` finds what it's looking for in one.
+    fn wait_for<T>(
+        &mut self,
+        timeout: Duration,
+        mut reply: impl FnMut(&str) -> Option<T>,
+    ) -> anyhow::Result<T> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(left) {
+                Ok(line) => {
+                    if let Some(found) = reply(line.trim()) {
+                        return Ok(found);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    bail!("The engine didn't reply within {:?}", timeout)
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(self.exited()),
+            }
+        }
+    }
+
+    /// Why the engine stopped talking.
+    fn exited(&mut self) -> anyhow::Error {
+        // The output closes a moment before the process is done
+        thread::sleep(Duration::from_millis(50));
+
+        match self.child.try_wait() {
+            Ok(Some(status)) => anyhow!("The engine exited with {}", status),
+            _ => anyhow!("The engine stopped talking"),
+        }
+    }
+
+    /// Ask for a move in `board`.
+    fn request_move<const W: usize, const H: usize, const N: usize>(
+        &mut self,
+        board: &Board<W, H, N>,
+    ) -> anyhow::Result<BoardMove> {
+        // Anything left over from the last move isn't about this one
+        while self.lines.try_recv().is_ok() {}
+
+        self.send(&format!("position moves {}", board.to_moves()))?;
+        let go = self.go.clone();
+        self.send(&go)?;
+
+        let reply = self.wait_for(self.timeout, |line| {
+            line.strip_prefix("bestmove ").map(str::to_string)
+        })?;
+
+        match reply.trim().parse::<usize>() {
+            Ok(column) if column > 0 => Ok((column - 1).into()),
+            _ => bail!("The engine replied bestmove {} instead of a column", reply),
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const N: usize> Play<W, H, N> for External {
+    /// An engine that can't come up with a move plays off the board, which the
+    /// game takes as an illegal move. `get_action` forfeits instead.
+    fn get_move(&mut self, board: Board<W, H, N>) -> BoardMove {
+        self.request_move(&board).unwrap_or(W.into())
+    }
+
+    fn get_action(&mut self, board: Board<W, H, N>) -> PlayerAction {
+        match self.request_move(&board) {
+            Ok(move_data) => PlayerAction::Move(move_data),
+            Err(e) => {
+                // It can't be trusted to carry on after that
+                let _ = self.child.kill();
+                PlayerAction::Forfeit(e.to_string())
+            }
+        }
+    }
+
+    fn needs_to_see_board(&self) -> bool {
+        false
+    }
+
+    fn should_announce_move(&self) -> bool {
+        true
+    }
+}
+
+impl Drop for External {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        game::{Ending, Game},
+        player::random::Random,
+    };
+    use rstest::rstest;
+
+    /// A command running a shell script that answers `isready` and replies
+    /// to `go` with `reply`.
+    fn engine(name: &str, reply: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "quatre_con_engine_{}_{}.sh",
+            name,
+            std::process::id()
+        ));
+        let script = format!(
+            "while read line; do\n\
+             \x20 case \"$line\" in\n\
+             \x20   isready) echo readyok ;;\n\
+             \x20   go*) {} ;;\n\
+             \x20   quit) exit 0 ;;\n\
+             \x20 esac\n\
+             done\n",
+            reply
+        );
+        fs::write(&path, script).unwrap();
+
+        format!("sh {}", path.display())
+    }
+
+    fn action(name: &str, reply: &str, moves: &str) -> PlayerAction {
+        let mut external = External::new(
+            Piece::Yellow,
+            &engine(name, reply),
+            5,
+            Some(Duration::from_millis(100)),
+        )
+        .unwrap();
+
+        external.get_action(Board::<7, 6, 4>::from_moves(moves).unwrap())
+    }
+
+    #[test]
+    fn test_move() {
+        let reply = "echo info depth 1 score 0 pv 4; echo bestmove 4";
+        assert_eq!(action("move", reply, ""), PlayerAction::Move(3.into()));
+        assert_eq!(action("move", reply, "11"), PlayerAction::Move(3.into()));
+    }
+
+    #[rstest]
+    #[case("crash", "exit 3", "exited with exit status: 3")]
+    #[case("slow", "sleep 5", "didn't reply within")]
+    #[case("garbled", "echo bestmove none", "bestmove none instead of a column")]
+    fn test_forfeit(#[case] name: &str, #[case] reply: &str, #[case] reason: &str) {
+        let start = Instant::now();
+
+        match action(name, reply, "") {
+            PlayerAction::Forfeit(e) => assert!(e.contains(reason), "{}", e),
+            action => panic!("Expected a forfeit, got {:?}", action),
+        }
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_not_an_engine() {
+        assert!(External::new(Piece::Red, "sh -c true", 5, None).is_err());
+        assert!(External::new(Piece::Red, "./no_such_engine", 5, None).is_err());
+        assert!(External::new(Piece::Red, " ", 5, None).is_err());
+    }
+
+    #[rstest]
+    #[case("game_crash", "exit 1", Ending::Forfeit)]
+    #[case("game_illegal", "echo bestmove 9", Ending::IllegalMove)]
+    fn test_game_ending(#[case] name: &str, #[case] reply: &str, #[case] ending: Ending) {
+        let mut game: Game = Game {
+            board: Board::default(),
+            player1: Box::new(External::new(Piece::Yellow, &engine(name, reply), 5, None).unwrap()),
+            player2: Box::new(Random { color: Piece::Red }),
+            observers: vec![],
+        };
+
+        let result = game.run();
+
+        assert_eq!(result.ending, ending);
+        assert_eq!(result.winner, Some(Piece::Red));
+        assert!(result.moves.is_empty());
+    }
+}
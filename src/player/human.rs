@@ -5,34 +5,83 @@ use std::{
 };
 
 use crate::{
-    board::{board::Board, board_move::BoardMove},
-    game::{MovePiece, Play},
+    board::{
+        board::{Board, BoardError},
+        board_move::BoardMove,
+    },
+    game::{MovePiece, Play, PlayerAction},
+    observer::format_pv,
+    tree::{Algorithm, Tree},
 };
 
 #[derive(Debug)]
-pub struct Human {
+pub struct Human<const W: usize = 7, const H: usize = 6, const N: usize = 4> {
     pub name: String,
+    /// Searches for the `hint` command
+    hints: Tree<Board<W, H, N>, BoardMove, BoardError>,
 }
 
-impl Display for Human {
+impl<const W: usize, const H: usize, const N: usize> Human<W, H, N> {
+    /// `depth`, `alg` and `tt_size` are for the search behind hints and work
+    /// the same as a bot's.
+    pub fn new(name: String, depth: usize, alg: Algorithm, tt_size: usize) -> Self {
+        Self {
+            name,
+            hints: Tree::new(depth, alg, false, tt_size),
+        }
+    }
+
+    fn print_hint(&self, mut board: Board<W, H, N>) {
+        let lines = self.hints.analyze(&mut board);
+
+        let Some((best, _, _)) = lines.first() else {
+            return;
+        };
+
+        println!("Hint: play {}", best);
+        for (move_data, eval, pv) in &lines {
+            println!("  {}: {} pv: {}", move_data, eval, format_pv(pv));
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const N: usize> Display for Human<W, H, N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.name)
     }
 }
 
-impl Play for Human {
-    fn get_move(&mut self, board: Board) -> BoardMove {
+impl<const W: usize, const H: usize, const N: usize> Play<W, H, N> for Human<W, H, N> {
+    fn get_move(&mut self, board: Board<W, H, N>) -> BoardMove {
+        loop {
+            if let PlayerAction::Move(move_data) = self.get_action(board.clone()) {
+                return move_data;
+            }
+            println!("You can't undo right now");
+        }
+    }
+
+    fn get_action(&mut self, board: Board<W, H, N>) -> PlayerAction {
         let stdin = io::stdin();
         let mut lines = stdin.lines();
 
         while let Some(Ok(line)) = lines.next() {
-            match BoardMove::from_str(&line) {
+            match line.trim() {
+                "undo" | "u" => return PlayerAction::Undo,
+                "hint" | "h" => {
+                    self.print_hint(board.clone());
+                    continue;
+                }
+                _ => {}
+            }
+
+            match BoardMove::from_str(line.trim()) {
                 Ok(val) => {
                     if !board.is_move_valid(&val) {
                         println!("Invalid Move for current game state.");
                         continue;
                     }
-                    return val;
+                    return PlayerAction::Move(val);
                 }
                 Err(_) => {
                     println!("Invalid text entry");
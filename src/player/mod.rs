@@ -1,3 +1,6 @@
 pub mod bot;
+pub mod config;
+pub mod external;
 pub mod human;
 pub mod random;
+pub mod remote;
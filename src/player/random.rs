@@ -17,8 +17,8 @@ impl Display for Random {
     }
 }
 
-impl Play for Random {
-    fn get_move(&mut self, board: Board) -> BoardMove {
+impl<const W: usize, const H: usize, const N: usize> Play<W, H, N> for Random {
+    fn get_move(&mut self, board: Board<W, H, N>) -> BoardMove {
         board
             .list_moves()
             .into_iter()
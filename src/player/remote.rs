@@ -0,0 +1,225 @@
+use std::fmt::Display;
+
+use crate::{
+    board::{board::Board, board_move::BoardMove, piece::Piece},
+    game::{Ending, GameResult, Play, PlayerAction},
+    network::Connection,
+};
+
+/// A player on the other end of a network connection.
+///
+/// Each turn it sends the other side whatever happened on this side since
+/// its last turn, `move` for each move (columns from 1) and `undo` for each
+/// round taken back, then waits for theirs. Losing the connection forfeits
+/// the game.
+#[derive(Debug)]
+pub struct Remote {
+    pub color: Piece,
+    connection: Connection,
+    /// The moves the other side knows have been played
+    known: Vec<usize>,
+}
+
+impl Display for Remote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.color)
+    }
+}
+
+impl Remote {
+    /// `start` is the history of the board both sides agreed to start from.
+    pub fn new(color: Piece, connection: Connection, start: &[usize]) -> Self {
+        Self {
+            color,
+            connection,
+            known: start.to_vec(),
+        }
+    }
+
+    /// Bring the other side up to date with `history`.
+    fn catch_up(&mut self, history: &[usize]) -> anyhow::Result<()> {
+        while !history.starts_with(&self.known) {
+            self.connection.send("undo")?;
+            self.known.truncate(self.known.len().saturating_sub(2));
+        }
+
+        for &column in &history[self.known.len()..] {
+            self.connection.send(&format!("move {}", column + 1))?;
+            self.known.push(column);
+        }
+
+        Ok(())
+    }
+
+    fn receive_action(&mut self) -> anyhow::Result<PlayerAction> {
+        let line = self.connection.receive()?;
+
+        let action = match line.split_once(' ') {
+            Some(("move", column)) => match column.parse::<usize>() {
+                Ok(column) if column > 0 => {
+                    self.known.push(column - 1);
+                    PlayerAction::Move((column - 1).into())
+                }
+                _ => PlayerAction::Forfeit(format!("The other player sent {}", line)),
+            },
+            None if line == "undo" => {
+                self.known.truncate(self.known.len().saturating_sub(2));
+                PlayerAction::Undo
+            }
+            None if line == "resign" => PlayerAction::Forfeit("The other player resigned".into()),
+            _ => PlayerAction::Forfeit(format!("The other player sent {}", line)),
+        };
+
+        Ok(action)
+    }
+}
+
+impl<const W: usize, const H: usize, const N: usize> Play<W, H, N> for Remote {
+    /// The other player losing the connection plays off the board, which the
+    /// game takes as an illegal move. `get_action` forfeits instead.
+    fn get_move(&mut self, board: Board<W, H, N>) -> BoardMove {
+        match self.get_action(board) {
+            PlayerAction::Move(move_data) => move_data,
+            _ => W.into(),
+        }
+    }
+
+    fn get_action(&mut self, board: Board<W, H, N>) -> PlayerAction {
+        self.catch_up(board.history())
+            .and_then(|_| self.receive_action())
+            .unwrap_or_else(|e| PlayerAction::Forfeit(e.to_string()))
+    }
+
+    fn needs_to_see_board(&self) -> bool {
+        false
+    }
+
+    fn should_announce_move(&self) -> bool {
+        true
+    }
+
+    /// Pass on the move that ended the game, or that this side gave up. The
+    /// other side may well have gone already, so nothing here can fail.
+    fn on_game_over(&mut self, result: &GameResult<W, H, N>) {
+        let _ = self.catch_up(result.board.history());
+
+        let gave_up = matches!(result.ending, Ending::IllegalMove | Ending::Forfeit);
+        if gave_up && result.winner == Some(self.color) {
+            let _ = self.connection.send("resign");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use super::*;
+    use crate::{
+        game::Game,
+        player::{bot::Bot, random::Random},
+        tree::Algorithm,
+    };
+
+    /// Both ends of a connection on localhost.
+    fn connections() -> (Connection, Connection) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        (
+            Connection::new(server).unwrap(),
+            Connection::new(client).unwrap(),
+        )
+    }
+
+    fn bot(color: Piece) -> Box<Bot> {
+        Box::new(Bot::new(color, 3, Algorithm::AlphaBeta, false, None, 0, 1))
+    }
+
+    #[test]
+    fn test_game() {
+        let (host, joiner) = connections();
+        let start = Board::from_moves("44").unwrap();
+
+        let joiner_start = start.clone();
+        let joiner = thread::spawn(move || {
+            let mut game: Game = Game {
+                board: joiner_start,
+                player1: Box::new(Remote::new(Piece::Yellow, joiner, &[3, 3])),
+                player2: Box::new(Random { color: Piece::Red }),
+                observers: vec![],
+            };
+            game.run()
+        });
+
+        let mut game: Game = Game {
+            board: start,
+            player1: bot(Piece::Yellow),
+            player2: Box::new(Remote::new(Piece::Red, host, &[3, 3])),
+            observers: vec![],
+        };
+        let host_result = game.run();
+        let joiner_result = joiner.join().unwrap();
+
+        assert_eq!(host_result.board, joiner_result.board);
+        assert_eq!(host_result.winner, joiner_result.winner);
+        assert_ne!(host_result.ending, Ending::Forfeit);
+        assert_ne!(joiner_result.ending, Ending::Forfeit);
+    }
+
+    #[test]
+    fn test_undo() {
+        let (mut host, joiner) = connections();
+        let mut remote = Remote::new(Piece::Red, joiner, &[]);
+
+        host.send("move 4").unwrap();
+        let board = Board::<7, 6, 4>::from_moves("1").unwrap();
+        assert_eq!(remote.get_action(board), PlayerAction::Move(3.into()));
+        assert_eq!(host.receive().unwrap(), "move 1");
+
+        // This side took the round back and played 2 instead
+        host.send("move 5").unwrap();
+        let board = Board::<7, 6, 4>::from_moves("2").unwrap();
+        assert_eq!(remote.get_action(board), PlayerAction::Move(4.into()));
+        assert_eq!(host.receive().unwrap(), "undo");
+        assert_eq!(host.receive().unwrap(), "move 2");
+
+        host.send("undo").unwrap();
+        let board = Board::<7, 6, 4>::from_moves("2536").unwrap();
+        assert_eq!(remote.get_action(board), PlayerAction::Undo);
+        assert_eq!(host.receive().unwrap(), "move 3");
+        assert_eq!(remote.known, vec![1, 4]);
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let (host, joiner) = connections();
+        let mut remote = Remote::new(Piece::Red, joiner, &[]);
+        drop(host);
+
+        match remote.get_action(Board::<7, 6, 4>::from_moves("4").unwrap()) {
+            PlayerAction::Forfeit(reason) => assert!(
+                reason.contains("disconnected") || reason.contains("Lost the connection"),
+                "{}",
+                reason
+            ),
+            action => panic!("Expected a forfeit, got {:?}", action),
+        }
+    }
+
+    #[test]
+    fn test_resign() {
+        let (mut host, joiner) = connections();
+        let mut remote = Remote::new(Piece::Red, joiner, &[]);
+
+        host.send("resign").unwrap();
+        assert_eq!(
+            remote.get_action(Board::<7, 6, 4>::from_moves("4").unwrap()),
+            PlayerAction::Forfeit("The other player resigned".into())
+        );
+    }
+}
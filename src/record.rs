@@ -0,0 +1,320 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::board::{Board, BoardError},
+    board::piece::Piece,
+    game::{Ending, Evaluate, GameEvaluation, GameResult, MoveRecord, Play},
+    observer::GameObserver,
+    player::config::PlayerConfig,
+};
+
+/// The version of the record format written by this build.
+pub const RECORD_VERSION: u32 = 1;
+
+/// How a recorded game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Finish {
+    /// Yellow, who plays first, won
+    Yellow,
+    Red,
+    Draw,
+}
+
+impl Finish {
+    /// How the game on `board` ended, if it has.
+    pub fn of<const W: usize, const H: usize, const N: usize>(
+        board: &Board<W, H, N>,
+    ) -> Option<Self> {
+        match board.evaluate(false) {
+            GameEvaluation::Win(_) => Some(Self::Yellow),
+            GameEvaluation::Lose(_) => Some(Self::Red),
+            GameEvaluation::Draw => Some(Self::Draw),
+            _ => None,
+        }
+    }
+
+    /// How `result`'s game ended, including when nobody connected.
+    pub fn of_result<const W: usize, const H: usize, const N: usize>(
+        result: &GameResult<W, H, N>,
+    ) -> Self {
+        match result.winner {
+            Some(Piece::Yellow) => Self::Yellow,
+            Some(Piece::Red) => Self::Red,
+            None => Self::Draw,
+        }
+    }
+}
+
+/// A game as it is saved to disk, as JSON.
+///
+/// The moves are in the column sequence notation of [`Board::from_moves`], so
+/// replaying them from the empty board gives the position to resume from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub version: u32,
+    pub width: usize,
+    pub height: usize,
+    pub connect: usize,
+    pub player1: PlayerConfig,
+    pub player2: PlayerConfig,
+    pub moves: String,
+    /// Unset until the game is over
+    pub result: Option<Finish>,
+    /// Why the game ended, unset until it's over
+    #[serde(default)]
+    pub ending: Option<Ending>,
+    /// Seconds since the Unix epoch
+    pub started_at: u64,
+    /// Seconds since the Unix epoch
+    pub updated_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_secs())
+        .unwrap_or_default()
+}
+
+impl GameRecord {
+    pub fn new(
+        width: usize,
+        height: usize,
+        connect: usize,
+        player1: PlayerConfig,
+        player2: PlayerConfig,
+    ) -> Self {
+        let now = now();
+
+        Self {
+            version: RECORD_VERSION,
+            width,
+            height,
+            connect,
+            player1,
+            player2,
+            moves: String::new(),
+            result: None,
+            ending: None,
+            started_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read the game from {}", path.display()))?;
+        let record: Self = serde_json::from_str(&json)
+            .with_context(|| format!("Couldn't read the game from {}", path.display()))?;
+
+        if record.version > RECORD_VERSION {
+            bail!(
+                "{} was saved by a newer version (record version {})",
+                path.display(),
+                record.version
+            );
+        }
+
+        Ok(record)
+    }
+
+    /// Write the record to `path`, replacing the previous save in one go so an
+    /// interrupted write never loses it.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        fs::write(&tmp, json)
+            .and_then(|_| fs::rename(&tmp, path))
+            .with_context(|| format!("Couldn't save the game to {}", path.display()))
+    }
+
+    /// Bring the record up to date with `board`.
+    pub fn update<const W: usize, const H: usize, const N: usize>(
+        &mut self,
+        board: &Board<W, H, N>,
+    ) {
+        self.moves = board.to_moves();
+        self.result = Finish::of(board);
+        self.updated_at = now();
+    }
+
+    /// Record how the game ended, which the board alone can't tell when a
+    /// player forfeited.
+    pub fn finish<const W: usize, const H: usize, const N: usize>(
+        &mut self,
+        result: &GameResult<W, H, N>,
+    ) {
+        self.update(&result.board);
+        self.result = Some(Finish::of_result(result));
+        self.ending = Some(result.ending);
+    }
+
+    /// The board the recorded moves lead to.
+    pub fn board<const W: usize, const H: usize, const N: usize>(
+        &self,
+    ) -> anyhow::Result<Board<W, H, N>> {
+        if (self.width, self.height, self.connect) != (W, H, N) {
+            bail!(
+                "The game is {}x{} connect {}, not {}x{} connect {}",
+                self.width,
+                self.height,
+                self.connect,
+                W,
+                H,
+                N
+            );
+        }
+
+        Ok(Board::from_moves(&self.moves)?)
+    }
+}
+
+/// Where a game in progress is saved. As an observer it keeps the file up to
+/// date after every move.
+#[derive(Debug)]
+pub struct SaveFile {
+    pub path: PathBuf,
+    pub record: GameRecord,
+}
+
+impl SaveFile {
+    pub fn new(path: PathBuf, record: GameRecord) -> Self {
+        Self { path, record }
+    }
+
+    pub fn update<const W: usize, const H: usize, const N: usize>(
+        &mut self,
+        board: &Board<W, H, N>,
+    ) -> anyhow::Result<()> {
+        self.record.update(board);
+        self.record.save(&self.path)
+    }
+
+    /// Saving mustn't stop the game, so failures are only reported.
+    fn update_or_report<const W: usize, const H: usize, const N: usize>(
+        &mut self,
+        board: &Board<W, H, N>,
+    ) {
+        if let Err(e) = self.update(board) {
+            eprintln!("{:#}", e);
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const N: usize> GameObserver<W, H, N> for SaveFile {
+    fn on_game_start(
+        &mut self,
+        board: &Board<W, H, N>,
+        _player1: &dyn Play<W, H, N>,
+        _player2: &dyn Play<W, H, N>,
+    ) {
+        self.update_or_report(board);
+    }
+
+    fn on_move_played(
+        &mut self,
+        board: &Board<W, H, N>,
+        _player: &dyn Play<W, H, N>,
+        _record: &MoveRecord,
+    ) {
+        self.update_or_report(board);
+    }
+
+    fn on_undo(&mut self, board: &Board<W, H, N>, _undone: &Result<(), BoardError>) {
+        self.update_or_report(board);
+    }
+
+    fn on_game_over(
+        &mut self,
+        result: &GameResult<W, H, N>,
+        _player1: &dyn Play<W, H, N>,
+        _player2: &dyn Play<W, H, N>,
+    ) {
+        self.record.finish(result);
+        if let Err(e) = self.record.save(&self.path) {
+            eprintln!("{:#}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn record() -> GameRecord {
+        GameRecord::new(
+            7,
+            6,
+            4,
+            PlayerConfig::new("human", "alphabeta", 5, None, 16),
+            PlayerConfig::new("bot", "minimax", 3, Some(Duration::from_millis(500)), 0),
+        )
+    }
+
+    #[test]
+    fn test_update() {
+        let mut record = record();
+
+        record.update(&<Board>::from_moves("4453").unwrap());
+        assert_eq!(record.moves, "4453");
+        assert_eq!(record.result, None);
+
+        record.update(&<Board>::from_moves("1212121").unwrap());
+        assert_eq!(record.result, Some(Finish::Yellow));
+
+        assert_eq!(
+            record.board::<7, 6, 4>().unwrap(),
+            <Board>::from_moves("1212121").unwrap()
+        );
+        assert!(record.board::<8, 7, 4>().is_err());
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("quatre_con_{}.json", std::process::id()));
+
+        let mut save_file = SaveFile::new(path.clone(), record());
+        save_file
+            .update(&<Board>::from_moves("44").unwrap())
+            .unwrap();
+
+        let loaded = GameRecord::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, save_file.record);
+        assert_eq!(loaded.player2.move_time_ms, Some(500));
+        assert_eq!(loaded.moves, "44");
+    }
+
+    #[test]
+    fn test_finish_by_forfeit() {
+        let mut record = record();
+        let board = <Board>::from_moves("4453").unwrap();
+
+        record.finish(&GameResult {
+            winner: Some(Piece::Red),
+            ending: Ending::Forfeit,
+            moves: vec![],
+            plies: 4,
+            board,
+        });
+
+        // Nobody connected, but the game is over all the same
+        assert_eq!(record.moves, "4453");
+        assert_eq!(record.result, Some(Finish::Red));
+        assert_eq!(record.ending, Some(Ending::Forfeit));
+    }
+}
@@ -0,0 +1,555 @@
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::{
+    board::{
+        board::{Board, BoardError},
+        board_move::BoardMove,
+        piece::Piece,
+    },
+    game::{Evaluate, Game, MovePiece, PlayerAction},
+    player::config::PlayerConfig,
+    record::Finish,
+    tree::{Algorithm, SearchReport, Tree},
+};
+
+/// The page playing the game in the browser, everything it needs included.
+const INDEX: &str = include_str!("../static/index.html");
+
+/// Every path that's answered, to tell a wrong method from a wrong path.
+const ROUTES: [&str; 7] = [
+    "/",
+    "/api/state",
+    "/api/new",
+    "/api/move",
+    "/api/bot",
+    "/api/undo",
+    "/api/analysis",
+];
+
+/// How many MB the transposition table behind analysis may use.
+const ANALYSIS_TT_SIZE: usize = 16;
+
+/// The most a request may ask the bot or analysis to search with, so no page
+/// can tie the server up for good or take all its memory.
+const MAX_DEPTH: usize = 12;
+const MAX_ITERATIONS: usize = 1_000_000;
+const MAX_MOVE_TIME_MS: u64 = 10_000;
+const MAX_THREADS: usize = 8;
+const MAX_TT_SIZE: usize = 256;
+
+/// A move's column as the API numbers it, from 1 like the move notation.
+fn api_column(move_data: &BoardMove) -> usize {
+    move_data.column + 1
+}
+
+/// The game as the API shows it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct State {
+    pub width: usize,
+    pub height: usize,
+    pub connect: usize,
+    /// The columns played so far in the same notation as `--position`
+    pub moves: String,
+    /// The board from the top row down, `Y`, `R` or `_` per square, as in a
+    /// board file
+    pub rows: Vec<String>,
+    /// `yellow` or `red`
+    pub to_play: String,
+    /// The columns that can be played, 1 being the leftmost
+    pub legal: Vec<usize>,
+    /// The evaluation without searching
+    pub eval: String,
+    /// Unset until the game is over
+    pub result: Option<Finish>,
+}
+
+impl State {
+    pub fn of<const W: usize, const H: usize, const N: usize>(board: &Board<W, H, N>) -> Self {
+        let mut columns = vec![vec![]; W];
+        for (ply, &column) in board.history().iter().enumerate() {
+            columns[column].push(if ply % 2 == 0 { 'Y' } else { 'R' });
+        }
+        let rows = (0..H)
+            .rev()
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|pieces| pieces.get(row).copied().unwrap_or('_'))
+                    .collect()
+            })
+            .collect();
+
+        let over = board.is_over();
+
+        Self {
+            width: W,
+            height: H,
+            connect: N,
+            moves: board.to_moves(),
+            rows,
+            to_play: board.whos_to_play().name().to_string(),
+            legal: match over {
+                true => vec![],
+                false => board.list_moves().iter().map(api_column).collect(),
+            },
+            eval: board.evaluate(false).to_string(),
+            result: Finish::of(board),
+        }
+    }
+}
+
+/// What the bot found behind its move.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Search {
+    pub depth: usize,
+    pub eval: String,
+    pub pv: Vec<usize>,
+    pub nodes: usize,
+}
+
+impl From<SearchReport<BoardMove>> for Search {
+    fn from(report: SearchReport<BoardMove>) -> Self {
+        Self {
+            depth: report.depth,
+            eval: report.eval.to_string(),
+            pv: report.pv.iter().map(api_column).collect(),
+            nodes: report.nodes,
+        }
+    }
+}
+
+/// The game after the bot's move.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BotMove {
+    /// The column the bot played
+    pub column: usize,
+    /// Unset for bots that don't search
+    pub search: Option<Search>,
+    pub state: State,
+}
+
+/// One move of an analysis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Line {
+    pub column: usize,
+    pub eval: String,
+    pub pv: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct NewGame {
+    /// Played on from the server's starting position
+    moves: String,
+    /// A player spec as in a tournament, e.g. `bot:depth=7`
+    bot: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PlayMove {
+    column: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorReply {
+    error: String,
+}
+
+/// A reply to a request, its status and JSON or HTML body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Reply {
+    fn json(value: &impl Serialize) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: serde_json::to_string(value).unwrap(),
+        }
+    }
+
+    fn error(status: u16, error: impl ToString) -> Self {
+        Self {
+            status,
+            ..Self::json(&ErrorReply {
+                error: error.to_string(),
+            })
+        }
+    }
+}
+
+/// One game in the browser against the bot, behind a JSON API.
+///
+/// The game's players are both the bot, so it can move for either side
+/// whenever it's asked, and moves from the page are played straight on the
+/// game's board.
+pub struct WebGame<const W: usize = 7, const H: usize = 6, const N: usize = 4> {
+    /// Where new games start from
+    start: Board<W, H, N>,
+    game: Game<W, H, N>,
+    bot: PlayerConfig,
+    analysis_depth: usize,
+}
+
+impl<const W: usize, const H: usize, const N: usize> WebGame<W, H, N> {
+    pub fn new(
+        start: Board<W, H, N>,
+        bot: PlayerConfig,
+        analysis_depth: usize,
+    ) -> anyhow::Result<Self> {
+        let game = Self::game(start.clone(), &bot)?;
+
+        Ok(Self {
+            start,
+            game,
+            bot,
+            analysis_depth,
+        })
+    }
+
+    fn game(board: Board<W, H, N>, bot: &PlayerConfig) -> anyhow::Result<Game<W, H, N>> {
+        // A human would wait for the server's stdin
+        if bot.kind == "human" {
+            bail!("The bot can't be a human, the page is for them");
+        }
+
+        Ok(Game {
+            board,
+            player1: bot.build(Piece::Yellow)?,
+            player2: bot.build(Piece::Red)?,
+            observers: vec![],
+        })
+    }
+
+    /// Answer the request for `method` and `url` with `body`.
+    pub fn handle(&mut self, method: &Method, url: &str, body: &str) -> Reply {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+        let reply = match (method, path) {
+            (Method::Get, "/") => {
+                return Reply {
+                    status: 200,
+                    content_type: "text/html; charset=utf-8",
+                    body: INDEX.to_string(),
+                }
+            }
+            (Method::Get, "/api/state") => Ok(Reply::json(&State::of(&self.game.board))),
+            (Method::Post, "/api/new") => self.new_game(body),
+            (Method::Post, "/api/move") => self.play(body),
+            (Method::Post, "/api/bot") => self.bot_move(),
+            (Method::Post, "/api/undo") => self.undo(),
+            (Method::Get, "/api/analysis") => self.analysis(query),
+            (_, path) if ROUTES.contains(&path) => return Reply::error(405, "Method not allowed"),
+            _ => return Reply::error(404, "Not found"),
+        };
+
+        reply.unwrap_or_else(|e| Reply::error(400, e))
+    }
+
+    fn new_game(&mut self, body: &str) -> anyhow::Result<Reply> {
+        let request: NewGame = match body.trim() {
+            "" => NewGame::default(),
+            body => serde_json::from_str(body)?,
+        };
+
+        let bot = match &request.bot {
+            Some(spec) => Self::requested_bot(spec)?,
+            None => self.bot.clone(),
+        };
+        let mut board = self.start.clone();
+        board.play_moves(&request.moves)?;
+
+        self.game = Self::game(board, &bot)?;
+        self.bot = bot;
+
+        Ok(Reply::json(&State::of(&self.game.board)))
+    }
+
+    /// A bot from a request's spec. Only the bot and random players can be
+    /// asked for, an engine would run any command it was given, and its
+    /// settings are kept within the server's limits.
+    fn requested_bot(spec: &str) -> anyhow::Result<PlayerConfig> {
+        let mut bot: PlayerConfig = spec.parse()?;
+        if !matches!(bot.kind.as_str(), "bot" | "random") {
+            bail!("The bot can only be bot or random, not {}", bot.kind);
+        }
+
+        bot.depth = bot.depth.clamp(1, MAX_DEPTH);
+        bot.iterations = bot.iterations.clamp(1, MAX_ITERATIONS);
+        bot.move_time_ms = bot.move_time_ms.map(|t| t.min(MAX_MOVE_TIME_MS));
+        bot.threads = bot.threads.clamp(1, MAX_THREADS);
+        bot.tt_size = bot.tt_size.min(MAX_TT_SIZE);
+
+        Ok(bot)
+    }
+
+    fn play(&mut self, body: &str) -> anyhow::Result<Reply> {
+        let request: PlayMove = serde_json::from_str(body)?;
+
+        if self.game.board.is_over() {
+            bail!("The game is over");
+        }
+        let column = match request.column {
+            0 => bail!("Columns are numbered from 1"),
+            column => column - 1,
+        };
+        self.game.board.apply_move(&column.into())?;
+
+        Ok(Reply::json(&State::of(&self.game.board)))
+    }
+
+    fn bot_move(&mut self) -> anyhow::Result<Reply> {
+        let board = &self.game.board;
+        if board.is_over() {
+            bail!("The game is over");
+        }
+
+        let bot = match board.whos_to_play() {
+            Piece::Yellow => &mut self.game.player1,
+            Piece::Red => &mut self.game.player2,
+        };
+        let move_data = match bot.get_action(board.clone()) {
+            PlayerAction::Move(move_data) => move_data,
+            PlayerAction::Undo => bail!("The bot tried to undo"),
+            PlayerAction::Forfeit(reason) => bail!("The bot gave up: {}", reason),
+        };
+        let search = bot.search_report().map(Search::from);
+        self.game
+            .board
+            .apply_move(&move_data)
+            .map_err(|e| anyhow!("The bot played {}: {}", move_data, e))?;
+
+        Ok(Reply::json(&BotMove {
+            column: api_column(&move_data),
+            search,
+            state: State::of(&self.game.board),
+        }))
+    }
+
+    fn undo(&mut self) -> anyhow::Result<Reply> {
+        self.game.undo_round()?;
+
+        Ok(Reply::json(&State::of(&self.game.board)))
+    }
+
+    /// Every legal move, best first for whoever is to play, searched to the
+    /// `depth` in the query or the server's analysis depth.
+    fn analysis(&self, query: &str) -> anyhow::Result<Reply> {
+        let depth = match query.split('&').find_map(|q| q.strip_prefix("depth=")) {
+            Some(depth) => depth.parse::<usize>()?.clamp(1, MAX_DEPTH),
+            None => self.analysis_depth,
+        };
+
+        let tree: Tree<Board<W, H, N>, BoardMove, BoardError> = Tree::new(
+            depth,
+            Algorithm::AlphaBeta,
+            false,
+            ANALYSIS_TT_SIZE * 1024 * 1024,
+        );
+        let lines: Vec<Line> = tree
+            .analyze(&mut self.game.board.clone())
+            .into_iter()
+            .map(|(move_data, eval, pv)| Line {
+                column: api_column(&move_data),
+                eval: eval.to_string(),
+                pv: pv.iter().map(api_column).collect(),
+            })
+            .collect();
+
+        Ok(Reply::json(&lines))
+    }
+
+    /// Answer requests from `server` one at a time until it's shut down.
+    ///
+    /// Requests that change the game have to be sent as JSON, which other
+    /// sites' pages can't do without the browser asking the server first, so
+    /// they can't play on the page's game behind its back.
+    pub fn serve(&mut self, server: &tiny_http::Server) {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            let reply = if *request.method() == Method::Post && !is_json(&request) {
+                Reply::error(415, "Requests have to be sent as application/json")
+            } else {
+                match request.as_reader().read_to_string(&mut body) {
+                    Ok(_) => self.handle(request.method(), request.url(), &body),
+                    Err(e) => Reply::error(400, e),
+                }
+            };
+
+            respond(request, reply);
+        }
+    }
+}
+
+/// Whether `request` says its body is JSON.
+fn is_json(request: &Request) -> bool {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Content-Type"))
+        .and_then(|header| header.value.as_str().split(';').next())
+        .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case("application/json"))
+}
+
+/// Send `reply`, the browser having gone being no reason to stop serving.
+fn respond(request: Request, reply: Reply) {
+    let content_type = Header::from_bytes("Content-Type", reply.content_type).unwrap();
+    let response = Response::from_string(reply.body)
+        .with_status_code(reply.status)
+        .with_header(content_type);
+
+    let _ = request.respond(response);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn web_game(moves: &str) -> WebGame {
+        WebGame::new(
+            Board::from_moves(moves).unwrap(),
+            "bot:depth=3".parse().unwrap(),
+            4,
+        )
+        .unwrap()
+    }
+
+    fn state(reply: Reply) -> State {
+        assert_eq!(reply.status, 200, "{}", reply.body);
+        serde_json::from_str(&reply.body).unwrap()
+    }
+
+    #[test]
+    fn test_state() {
+        let state = State::of(&Board::<7, 6, 4>::from_moves("4453").unwrap());
+
+        assert_eq!(state.moves, "4453");
+        assert_eq!(state.rows[4], "___R___");
+        assert_eq!(state.rows[5], "__RYY__");
+        assert_eq!(state.to_play, "yellow");
+        assert_eq!(state.legal, vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(state.result, None);
+
+        let state = State::of(&Board::<7, 6, 4>::from_moves("1212121").unwrap());
+        assert_eq!(state.result, Some(Finish::Yellow));
+        assert!(state.legal.is_empty());
+    }
+
+    #[test]
+    fn test_play_and_undo() {
+        let mut web_game = web_game("");
+
+        let played = state(web_game.handle(&Method::Post, "/api/move", r#"{"column": 4}"#));
+        assert_eq!(played.moves, "4");
+
+        let reply = web_game.handle(&Method::Post, "/api/bot", "");
+        let bot_move: BotMove = serde_json::from_str(&reply.body).unwrap();
+        assert_eq!(bot_move.state.moves.len(), 2);
+        assert_eq!(bot_move.search.unwrap().pv[0], bot_move.column);
+
+        let undone = state(web_game.handle(&Method::Post, "/api/undo", ""));
+        assert_eq!(undone.moves, "");
+    }
+
+    #[rstest]
+    #[case(Method::Post, "/api/move", r#"{"column": 8}"#, 400)]
+    #[case(Method::Post, "/api/move", r#"{"column": 0}"#, 400)]
+    #[case(Method::Post, "/api/move", "4", 400)]
+    #[case(Method::Post, "/api/undo", "", 400)]
+    #[case(Method::Post, "/api/new", r#"{"bot": "human"}"#, 400)]
+    #[case(Method::Post, "/api/new", r#"{"bot": "engine:command=echo"}"#, 400)]
+    #[case(Method::Post, "/api/new", r#"{"moves": "48"}"#, 400)]
+    #[case(Method::Get, "/api/analysis?depth=deep", "", 400)]
+    #[case(Method::Get, "/api/move", "", 405)]
+    #[case(Method::Get, "/api/nothing", "", 404)]
+    fn test_errors(
+        #[case] method: Method,
+        #[case] url: &str,
+        #[case] body: &str,
+        #[case] status: u16,
+    ) {
+        let mut web_game = web_game("4");
+
+        let reply = web_game.handle(&method, url, body);
+
+        assert_eq!(reply.status, status);
+        assert!(reply.body.contains("\"error\""), "{}", reply.body);
+        // The game is untouched
+        assert_eq!(State::of(&web_game.game.board).moves, "4");
+    }
+
+    #[test]
+    fn test_game_over() {
+        let mut web_game = web_game("1212121");
+
+        assert_eq!(web_game.handle(&Method::Post, "/api/bot", "").status, 400);
+        let reply = web_game.handle(&Method::Post, "/api/move", r#"{"column": 4}"#);
+        assert_eq!(reply.status, 400);
+    }
+
+    #[test]
+    fn test_new_game() {
+        let mut web_game = web_game("4");
+
+        let new = state(web_game.handle(
+            &Method::Post,
+            "/api/new",
+            r#"{"moves": "53", "bot": "random"}"#,
+        ));
+
+        // Played on from where the server starts
+        assert_eq!(new.moves, "453");
+        assert_eq!(web_game.bot.kind, "random");
+        assert_eq!(
+            state(web_game.handle(&Method::Post, "/api/new", "")).moves,
+            "4"
+        );
+    }
+
+    #[test]
+    fn test_requested_bot_limits() {
+        let bot = WebGame::<7, 6, 4>::requested_bot(
+            "bot:depth=0,iterations=0,move-time=60m,threads=0,tt-size=100000",
+        )
+        .unwrap();
+
+        assert_eq!(bot.depth, 1);
+        assert_eq!(bot.iterations, 1);
+        assert_eq!(bot.move_time_ms, Some(MAX_MOVE_TIME_MS));
+        assert_eq!(bot.threads, 1);
+        assert_eq!(bot.tt_size, MAX_TT_SIZE);
+
+        let bot = WebGame::<7, 6, 4>::requested_bot("bot:depth=99,threads=1000").unwrap();
+        assert_eq!(bot.depth, MAX_DEPTH);
+        assert_eq!(bot.threads, MAX_THREADS);
+    }
+
+    #[test]
+    fn test_analysis_depth_zero() {
+        let mut web_game = web_game("4");
+
+        let reply = web_game.handle(&Method::Get, "/api/analysis?depth=0", "");
+        let lines: Vec<Line> = serde_json::from_str(&reply.body).unwrap();
+
+        assert_eq!(lines.len(), 7);
+    }
+
+    #[test]
+    fn test_analysis() {
+        let mut web_game = web_game("112233");
+
+        let reply = web_game.handle(&Method::Get, "/api/analysis?depth=2", "");
+        let lines: Vec<Line> = serde_json::from_str(&reply.body).unwrap();
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0].column, 4);
+        assert_eq!(lines[0].eval, "+M1");
+    }
+}
@@ -0,0 +1,405 @@
+use std::{
+    cmp::Reverse,
+    fmt::{Debug, Display},
+    hash::Hash,
+    marker::PhantomData,
+};
+
+use crate::{
+    game::{GameBoard, GameEvaluation, MoM},
+    transposition::{self, Bound, TranspositionTable},
+};
+
+/// How many bytes `solve` lets its transposition table use.
+pub const DEFAULT_TT_SIZE: usize = 64 * 1024 * 1024;
+
+/// The result of a game with perfect play, for the player to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// How a position ends with perfect play: the winner wins as fast as they
+/// can and the loser holds on as long as they can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Solution {
+    /// The result for the player to move.
+    pub outcome: Outcome,
+    /// How many moves, from both players, until the game ends.
+    pub plies: usize,
+}
+
+impl Display for Solution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = match self.outcome {
+            Outcome::Win => "Win",
+            Outcome::Loss => "Loss",
+            Outcome::Draw => "Draw",
+        };
+        write!(f, "{} in {} plies", outcome, self.plies)
+    }
+}
+
+impl Solution {
+    /// How many moves the winner makes before the game ends, counted the way
+    /// [`GameEvaluation::forced_win`] counts them.
+    ///
+    /// [`GameEvaluation::forced_win`]: crate::game::GameEvaluation::forced_win
+    pub fn winner_moves(&self) -> usize {
+        self.plies.div_ceil(2)
+    }
+}
+
+/// Solve `board` exactly with the default transposition table size.
+pub fn solve<B, D, E>(board: &mut B) -> Solution
+where
+    D: Clone + Debug,
+    E: Debug,
+    B: Hash + Clone + GameBoard<D, E>,
+{
+    Solver::new(DEFAULT_TT_SIZE).solve(board)
+}
+
+/// A perfect play solver on top of `MovePiece` and `Evaluate`.
+///
+/// Scores are from the point of view of the player to move. With `r` plies
+/// left until the board is full, winning on the `p`th ply from now is worth
+/// `r + 1 - p`, losing on it is the negative of that and a draw is 0. That way
+/// faster wins and slower losses score higher and a position's score doesn't
+/// depend on where the search started.
+#[derive(Debug)]
+pub struct Solver<B, D, E> {
+    transposition_table: TranspositionTable<i32>,
+    nodes: usize,
+    ghost: PhantomData<(B, D, E)>,
+}
+
+impl<B, D, E> Solver<B, D, E>
+where
+    D: Clone + Debug,
+    E: Debug,
+    B: Hash + Clone + GameBoard<D, E>,
+{
+    /// `tt_size` is how many bytes the transposition table may use.
+    pub fn new(tt_size: usize) -> Self {
+        Self {
+            transposition_table: TranspositionTable::new(tt_size),
+            nodes: 0,
+            ghost: PhantomData,
+        }
+    }
+
+    /// How many positions the last solve searched.
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn solve(&mut self, board: &mut B) -> Solution {
+        self.nodes = 0;
+
+        // The last move already finished the game
+        match board.evaluate(false) {
+            GameEvaluation::Win(_) | GameEvaluation::Lose(_) => {
+                return Solution {
+                    outcome: Outcome::Loss,
+                    plies: 0,
+                }
+            }
+            GameEvaluation::Draw => {
+                return Solution {
+                    outcome: Outcome::Draw,
+                    plies: 0,
+                }
+            }
+            _ => {}
+        }
+
+        let remaining = Self::remaining(board);
+
+        // Narrow down on the score with null window searches, leaning
+        // towards zero as most positions are closer to a draw than a mate.
+        let (mut min, mut max) = (-(remaining as i32), remaining as i32);
+        while min < max {
+            let mut med = min + (max - min) / 2;
+            if med <= 0 && min / 2 < med {
+                med = min / 2;
+            } else if med >= 0 && max / 2 > med {
+                med = max / 2;
+            }
+
+            let score = self.negamax(board, remaining, med, med + 1);
+            if score <= med {
+                max = score;
+            } else {
+                min = score;
+            }
+        }
+
+        Self::solution(min, remaining)
+    }
+
+    fn solution(score: i32, remaining: usize) -> Solution {
+        let remaining = remaining as i32;
+
+        let (outcome, plies) = match score {
+            0 => (Outcome::Draw, remaining),
+            s if s > 0 => (Outcome::Win, remaining + 1 - s),
+            s => (Outcome::Loss, remaining + 1 + s),
+        };
+
+        Solution {
+            outcome,
+            plies: plies as usize,
+        }
+    }
+
+    /// How many moves are left before the board is full.
+    fn remaining(board: &B) -> usize {
+        let mut board = board.clone();
+        let mut remaining = 0;
+
+        while let Some(m) = board.list_moves().first() {
+            board
+                .apply_move(m)
+                .expect("This should never fail as it is only valid moves");
+            remaining += 1;
+        }
+
+        remaining
+    }
+
+    /// Whether the player who just moved as `mover` won with it.
+    fn has_won(eval: GameEvaluation, mover: &MoM) -> bool {
+        matches!(
+            (eval, mover),
+            (GameEvaluation::Win(_), MoM::Max) | (GameEvaluation::Lose(_), MoM::Min)
+        )
+    }
+
+    /// The evaluation of a position from `mover`'s side, for move ordering.
+    fn heuristic(eval: GameEvaluation, mover: &MoM) -> isize {
+        match (eval, mover) {
+            (GameEvaluation::OnGoing(v), MoM::Max) => v,
+            (GameEvaluation::OnGoing(v), MoM::Min) => -v,
+            _ => 0,
+        }
+    }
+
+    /// The score of `board` if it is within `alpha` and `beta`. Otherwise a
+    /// bound past whichever side of the window it falls on.
+    fn negamax(&mut self, board: &mut B, remaining: usize, mut alpha: i32, mut beta: i32) -> i32 {
+        self.nodes += 1;
+
+        // No one won with the last move, which filled the board
+        if remaining == 0 {
+            return 0;
+        }
+
+        let mover = board.min_or_maxing();
+
+        // Take a win if there is one, and order the rest best first
+        let mut children = vec![];
+        for m in board.list_moves() {
+            board
+                .apply_move(&m)
+                .expect("This should never fail as it is only valid moves");
+            let eval = board.evaluate(true);
+            board
+                .remove_move(&m)
+                .expect("This should never fail as it is only valid moves");
+
+            if Self::has_won(eval, &mover) {
+                return remaining as i32;
+            }
+
+            children.push((Self::heuristic(eval, &mover), m));
+        }
+
+        // Without a win now the best left is a win on our next move
+        let max = (remaining as i32 - 2).max(0);
+        if beta > max {
+            beta = max;
+            if alpha >= beta {
+                return beta;
+            }
+        }
+
+        let key = transposition::key(board);
+        if let Some(entry) = self.transposition_table.probe(key, 0) {
+            match entry.bound {
+                Bound::Exact => return entry.value,
+                Bound::Lower => alpha = alpha.max(entry.value),
+                Bound::Upper => beta = beta.min(entry.value),
+            }
+
+            if alpha >= beta {
+                return entry.value;
+            }
+        }
+
+        let window_alpha = alpha;
+
+        children.sort_by_key(|(h, _)| Reverse(*h));
+
+        for (_, m) in children {
+            board
+                .apply_move(&m)
+                .expect("This should never fail as it is only valid moves");
+            let score = -self.negamax(board, remaining - 1, -beta, -alpha);
+            board
+                .remove_move(&m)
+                .expect("This should never fail as it is only valid moves");
+
+            if score >= beta {
+                self.transposition_table
+                    .store(key, 0, score, Bound::Lower, None);
+                return score;
+            }
+
+            alpha = alpha.max(score);
+        }
+
+        let bound = if alpha > window_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.transposition_table.store(key, 0, alpha, bound, None);
+
+        alpha
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        board::{
+            board::{Board, BoardError},
+            board_move::BoardMove,
+        },
+        game::{Evaluate, MovePiece},
+    };
+    use rstest::rstest;
+
+    /// Plain negamax to the end of the game, with the solver's scores.
+    fn brute_force<const W: usize, const H: usize, const N: usize>(
+        board: &mut Board<W, H, N>,
+        remaining: usize,
+        seen: &mut HashMap<Board<W, H, N>, i32>,
+    ) -> i32 {
+        if remaining == 0 {
+            return 0;
+        }
+        if let Some(score) = seen.get(board) {
+            return *score;
+        }
+
+        let mover = board.min_or_maxing();
+
+        let mut best = i32::MIN;
+        for m in board.list_moves() {
+            board.apply_move(&m).unwrap();
+            let score = if Solver::<Board<W, H, N>, BoardMove, BoardError>::has_won(
+                board.evaluate(false),
+                &mover,
+            ) {
+                remaining as i32
+            } else {
+                -brute_force(board, remaining - 1, seen)
+            };
+            board.remove_move(&m).unwrap();
+
+            best = best.max(score);
+        }
+
+        seen.insert(board.clone(), best);
+        best
+    }
+
+    #[rstest]
+    #[case(&[0, 0, 1, 1, 2, 2], Outcome::Win, 1)]
+    #[case(&[1, 1, 2, 2, 3], Outcome::Loss, 2)]
+    #[case(&[0, 1, 0, 1, 0, 1, 0], Outcome::Loss, 0)]
+    fn test_solve(#[case] moves: &[usize], #[case] outcome: Outcome, #[case] plies: usize) {
+        let mut board: Board = Board::from_columns(moves);
+
+        assert_eq!(solve(&mut board), Solution { outcome, plies });
+    }
+
+    #[rstest]
+    #[case(Outcome::Win, 1, GameEvaluation::Win(1))]
+    #[case(Outcome::Loss, 2, GameEvaluation::Lose(2))]
+    #[case(Outcome::Win, 5, GameEvaluation::Win(5))]
+    fn test_winner_moves(
+        #[case] outcome: Outcome,
+        #[case] plies: usize,
+        #[case] eval: GameEvaluation,
+    ) {
+        let solution = Solution { outcome, plies };
+
+        assert_eq!(
+            Some(solution.winner_moves()),
+            eval.forced_win().map(|(_, moves)| moves)
+        );
+    }
+
+    #[test]
+    fn test_solve_full_board() {
+        // Fill the 4x4 board column by column without anyone connecting 3
+        let mut board: Board<4, 4, 3> =
+            Board::from_columns(&[0, 1, 0, 1, 1, 0, 1, 0, 2, 3, 2, 3, 3, 2, 3, 2]);
+
+        assert_eq!(
+            solve(&mut board),
+            Solution {
+                outcome: Outcome::Draw,
+                plies: 0
+            }
+        );
+    }
+
+    #[rstest]
+    #[case(&[])]
+    #[case(&[1])]
+    #[case(&[1, 2])]
+    #[case(&[0, 0, 3])]
+    #[case(&[2, 1, 1, 2])]
+    fn test_solve_matches_brute_force_4x4(#[case] moves: &[usize]) {
+        let mut board: Board<4, 4, 3> = Board::from_columns(moves);
+
+        let remaining = 16 - moves.len();
+        let expected = brute_force(&mut board, remaining, &mut HashMap::new());
+
+        let mut solver = Solver::new(1024 * 1024);
+        let solution = solver.solve(&mut board);
+
+        assert_eq!(
+            solution,
+            Solver::<Board<4, 4, 3>, BoardMove, BoardError>::solution(expected, remaining)
+        );
+    }
+
+    #[rstest]
+    #[case(&[2, 2, 1, 3, 3, 1, 0, 4])]
+    #[case(&[2, 2, 2, 2, 1, 3, 0])]
+    #[case(&[0, 4, 1, 3, 2, 2, 1, 1])]
+    fn test_solve_matches_brute_force_5x4(#[case] moves: &[usize]) {
+        let mut board: Board<5, 4, 4> = Board::from_columns(moves);
+
+        let remaining = 20 - moves.len();
+        let expected = brute_force(&mut board, remaining, &mut HashMap::new());
+
+        let mut solver = Solver::new(1024 * 1024);
+        let solution = solver.solve(&mut board);
+
+        assert_eq!(
+            solution,
+            Solver::<Board<5, 4, 4>, BoardMove, BoardError>::solution(expected, remaining)
+        );
+    }
+}
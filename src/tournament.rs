@@ -0,0 +1,386 @@
+use std::fmt::Display;
+
+use anyhow::bail;
+
+use crate::{
+    board::{board::Board, piece::Piece},
+    game::{Game, GameResult, MovePiece},
+    player::config::PlayerConfig,
+};
+
+/// How one player did against another.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Score {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Score {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// A win is worth a point and a draw half a point.
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    fn add(&mut self, other: Score) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+
+    /// The same games from the other player's side.
+    fn flipped(&self) -> Score {
+        Score {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+        }
+    }
+
+    /// An Elo difference from this score, with the margin of its 95%
+    /// confidence interval.
+    ///
+    /// A perfect or hopeless score has no finite estimate and comes out as
+    /// plus or minus infinity.
+    pub fn elo(&self) -> (f64, f64) {
+        let n = self.games() as f64;
+        if n == 0.0 {
+            return (0.0, f64::INFINITY);
+        }
+
+        let p = self.points() / n;
+
+        // The spread of a single game's points around the mean
+        let variance = (self.wins as f64 * (1.0 - p).powi(2)
+            + self.draws as f64 * (0.5 - p).powi(2)
+            + self.losses as f64 * p.powi(2))
+            / n;
+        let margin = 1.96 * (variance / n).sqrt();
+
+        let elo = elo_difference(p);
+        if elo.is_infinite() {
+            return (elo, f64::INFINITY);
+        }
+
+        let low = elo_difference(p - margin);
+        let high = elo_difference(p + margin);
+
+        // Adding zero turns an even score's -0 into 0
+        (elo + 0.0, (high - low) / 2.0)
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}-{}", self.wins, self.draws, self.losses)
+    }
+}
+
+/// The Elo difference that makes `p` the expected score.
+fn elo_difference(p: f64) -> f64 {
+    -400.0 * (1.0 / p.clamp(0.0, 1.0) - 1.0).log10()
+}
+
+/// A round robin where every pair of players plays a number of games, taking
+/// turns to go first.
+///
+/// Each two games start from a different [`opening`], so bots that always
+/// pick the same move don't just play one game over and over.
+#[derive(Debug)]
+pub struct Tournament {
+    players: Vec<PlayerConfig>,
+    games_per_pairing: usize,
+    /// `scores[i][j]` is how player `i` did against player `j`
+    scores: Vec<Vec<Score>>,
+}
+
+impl Tournament {
+    pub fn new(players: Vec<PlayerConfig>, games_per_pairing: usize) -> anyhow::Result<Self> {
+        if players.len() < 2 {
+            bail!("A tournament needs at least two players");
+        }
+        if players.iter().any(|p| p.kind == "human") {
+            bail!("Humans can't play in tournaments");
+        }
+
+        let scores = vec![vec![Score::default(); players.len()]; players.len()];
+
+        Ok(Self {
+            players,
+            games_per_pairing,
+            scores,
+        })
+    }
+
+    pub fn players(&self) -> &[PlayerConfig] {
+        &self.players
+    }
+
+    /// How many games the whole tournament plays.
+    pub fn game_count(&self) -> usize {
+        let n = self.players.len();
+        n * (n - 1) / 2 * self.games_per_pairing
+    }
+
+    /// Play every game, calling `on_game` with the players' indices, yellow
+    /// first, after each one.
+    pub fn play<const W: usize, const H: usize, const N: usize>(
+        &mut self,
+        mut on_game: impl FnMut(usize, usize, &GameResult<W, H, N>),
+    ) -> anyhow::Result<()> {
+        for i in 0..self.players.len() {
+            for j in i + 1..self.players.len() {
+                for game in 0..self.games_per_pairing {
+                    let (yellow, red) = if game % 2 == 0 { (i, j) } else { (j, i) };
+
+                    let result = self.play_game(opening(game / 2), yellow, red)?;
+                    on_game(yellow, red, &result);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn play_game<const W: usize, const H: usize, const N: usize>(
+        &mut self,
+        board: Board<W, H, N>,
+        yellow: usize,
+        red: usize,
+    ) -> anyhow::Result<GameResult<W, H, N>> {
+        // Fresh players every game so no game benefits from an earlier one
+        let mut game = Game {
+            board,
+            player1: self.players[yellow].build(Piece::Yellow)?,
+            player2: self.players[red].build(Piece::Red)?,
+            observers: vec![],
+        };
+
+        let result = game.run();
+
+        let score = match result.winner {
+            Some(Piece::Yellow) => Score {
+                wins: 1,
+                ..Default::default()
+            },
+            Some(Piece::Red) => Score {
+                losses: 1,
+                ..Default::default()
+            },
+            None => Score {
+                draws: 1,
+                ..Default::default()
+            },
+        };
+        self.scores[yellow][red].add(score);
+        self.scores[red][yellow].add(score.flipped());
+
+        Ok(result)
+    }
+
+    /// How player `i` did against player `j`.
+    pub fn score(&self, i: usize, j: usize) -> Score {
+        self.scores[i][j]
+    }
+
+    /// How player `i` did against everyone.
+    pub fn total(&self, i: usize) -> Score {
+        let mut total = Score::default();
+        for score in &self.scores[i] {
+            total.add(*score);
+        }
+        total
+    }
+}
+
+/// The position the `pair`th pair of games between two players starts from:
+/// the empty board, then every opening of two moves, middle columns first,
+/// over again once they've all been played.
+pub fn opening<const W: usize, const H: usize, const N: usize>(pair: usize) -> Board<W, H, N> {
+    let mut board = Board::default();
+
+    let opening = pair % (W * W + 1);
+    if opening > 0 {
+        let mut columns: Vec<usize> = (0..W).collect();
+        columns.sort_by_key(|&c| (2 * c).abs_diff(W - 1));
+
+        for column in [columns[(opening - 1) / W], columns[(opening - 1) % W]] {
+            board.apply_move(&column.into()).unwrap();
+        }
+    }
+
+    board
+}
+
+/// The crosstable, each cell being the row player's wins, draws and losses
+/// against the column player, then everyone's totals and Elo against the field.
+impl Display for Tournament {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self.players.iter().map(|p| p.to_string()).collect();
+        let name_width = names.iter().map(|n| n.len()).max().unwrap_or(0);
+        let cell_width = self
+            .scores
+            .iter()
+            .flatten()
+            .map(|s| s.to_string().len())
+            .max()
+            .unwrap_or(0)
+            .max(3);
+
+        write!(f, "{:>4} {:name_width$}", "", "")?;
+        for j in 0..names.len() {
+            write!(f, " {:>cell_width$}", j + 1)?;
+        }
+        writeln!(f, " {:>10} {:>7} {:>14}", "W-D-L", "Points", "Elo")?;
+
+        for (i, name) in names.iter().enumerate() {
+            write!(f, "{:>3}. {:name_width$}", i + 1, name)?;
+            for j in 0..names.len() {
+                if i == j {
+                    write!(f, " {:>cell_width$}", "-")?;
+                } else {
+                    write!(f, " {:>cell_width$}", self.scores[i][j].to_string())?;
+                }
+            }
+
+            let total = self.total(i);
+            let (elo, margin) = total.elo();
+            writeln!(
+                f,
+                " {:>10} {:>7.1} {:>14}",
+                total.to_string(),
+                total.points(),
+                format!("{:+.0} ± {:.0}", elo, margin)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Score { wins: 5, draws: 0, losses: 5 }, 0.0)]
+    #[case(Score { wins: 0, draws: 10, losses: 0 }, 0.0)]
+    #[case(Score { wins: 3, draws: 0, losses: 1 }, 190.8)]
+    #[case(Score { wins: 1, draws: 0, losses: 3 }, -190.8)]
+    #[case(Score { wins: 2, draws: 2, losses: 0 }, 190.8)]
+    fn test_elo(#[case] score: Score, #[case] expected: f64) {
+        let (elo, _) = score.elo();
+
+        assert!((elo - expected).abs() < 0.1, "{} != {}", elo, expected);
+    }
+
+    #[test]
+    fn test_elo_margin() {
+        let (_, few) = Score {
+            wins: 3,
+            draws: 0,
+            losses: 1,
+        }
+        .elo();
+        let (_, many) = Score {
+            wins: 300,
+            draws: 0,
+            losses: 100,
+        }
+        .elo();
+
+        assert!(many < few);
+        assert!(many > 0.0);
+
+        let (elo, _) = Score {
+            wins: 4,
+            draws: 0,
+            losses: 0,
+        }
+        .elo();
+        assert_eq!(elo, f64::INFINITY);
+
+        let (elo, margin) = Score {
+            wins: 0,
+            draws: 0,
+            losses: 4,
+        }
+        .elo();
+        assert_eq!((elo, margin), (f64::NEG_INFINITY, f64::INFINITY));
+    }
+
+    #[test]
+    fn test_tournament() {
+        let players = ["bot:depth=4", "random", "bot:depth=2,alg=minimax"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let mut tournament = Tournament::new(players, 2).unwrap();
+
+        let mut firsts = vec![];
+        tournament
+            .play::<7, 6, 4>(|yellow, red, result| {
+                firsts.push((yellow, red));
+                assert!(result.moves.last().unwrap().eval.is_terminal());
+            })
+            .unwrap();
+
+        assert_eq!(tournament.game_count(), 6);
+        assert_eq!(firsts, vec![(0, 1), (1, 0), (0, 2), (2, 0), (1, 2), (2, 1)]);
+
+        for i in 0..3 {
+            assert_eq!(tournament.total(i).games(), 4);
+            for j in 0..3 {
+                assert_eq!(tournament.score(i, j), tournament.score(j, i).flipped());
+            }
+        }
+
+        let table = tournament.to_string();
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.contains("bot:depth=4"));
+    }
+
+    #[rstest]
+    #[case(0, "")]
+    #[case(1, "44")]
+    #[case(2, "43")]
+    #[case(3, "45")]
+    #[case(8, "34")]
+    #[case(49, "77")]
+    #[case(50, "")]
+    fn test_opening(#[case] pair: usize, #[case] moves: &str) {
+        assert_eq!(opening::<7, 6, 4>(pair).to_moves(), moves);
+    }
+
+    #[test]
+    fn test_games_start_from_openings() {
+        let players = ["bot:depth=3", "bot:depth=3,threats"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let mut tournament = Tournament::new(players, 4).unwrap();
+
+        let mut boards = vec![];
+        tournament
+            .play::<7, 6, 4>(|_, _, result| boards.push(result.board.clone()))
+            .unwrap();
+
+        // The second pair of games starts from 44, so plays out differently
+        assert_eq!(boards[2].history()[..2], [3, 3]);
+        assert_eq!(boards[3].history()[..2], [3, 3]);
+        assert_ne!(boards[0], boards[2]);
+    }
+
+    #[test]
+    fn test_no_humans() {
+        let players = vec![PlayerConfig::default(), "human".parse().unwrap()];
+
+        assert!(Tournament::new(players, 2).is_err());
+        assert!(Tournament::new(vec![PlayerConfig::default()], 2).is_err());
+    }
+}
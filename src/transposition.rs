@@ -0,0 +1,205 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    mem::size_of,
+};
+
+use crate::game::GameEvaluation;
+
+/// How a stored value relates to the real value of its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The search saw every move, so the value is the real one.
+    Exact,
+    /// The search failed high, so the real value is at least this.
+    Lower,
+    /// The search failed low, so the real value is at most this.
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry<V> {
+    key: u64,
+    pub depth: usize,
+    pub value: V,
+    pub bound: Bound,
+    /// Where the best move found is in the position's ordered moves
+    pub best_move: Option<u8>,
+}
+
+/// The key a position is stored under.
+pub fn key<B: Hash>(board: &B) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    board.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A fixed size table of search results keyed by position.
+///
+/// Each position hashes to a single slot and a new result always replaces
+/// whatever was in its slot, so the table never grows past its memory cap.
+#[derive(Debug)]
+pub struct TranspositionTable<V = GameEvaluation> {
+    entries: Vec<Option<Entry<V>>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<V: Copy> TranspositionTable<V> {
+    /// A table that fits in `memory_cap` bytes. A cap too small for a single
+    /// entry turns the table off.
+    pub fn new(memory_cap: usize) -> Self {
+        let slots = memory_cap / size_of::<Option<Entry<V>>>();
+
+        Self {
+            entries: vec![None; slots],
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.entries.is_empty()
+    }
+
+    /// How many bytes the table takes up.
+    pub fn memory(&self) -> usize {
+        self.entries.len() * size_of::<Option<Entry<V>>>()
+    }
+
+    /// How many positions the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    /// Look up a result for `key` searched at least `depth` deep.
+    pub fn probe(&mut self, key: u64, depth: usize) -> Option<Entry<V>> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let entry = self.entries[self.slot(key)].filter(|e| e.key == key && e.depth >= depth);
+
+        match entry {
+            Some(_) => self.hits += 1,
+            None => self.misses += 1,
+        }
+
+        entry
+    }
+
+    pub fn store(&mut self, key: u64, depth: usize, value: V, bound: Bound, best_move: Option<u8>) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let slot = self.slot(key);
+        self.entries[slot] = Some(Entry {
+            key,
+            depth,
+            value,
+            bound,
+            best_move,
+        });
+    }
+
+    /// The best move stored for `key`, however deep it was searched.
+    pub fn best_move(&self, key: u64) -> Option<u8> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        self.entries[self.slot(key)]
+            .filter(|e| e.key == key)
+            .and_then(|e| e.best_move)
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.reset_stats();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_cap() {
+        let entry_size = size_of::<Option<Entry<GameEvaluation>>>();
+
+        assert_eq!(<TranspositionTable>::new(100 * entry_size).capacity(), 100);
+        assert_eq!(
+            <TranspositionTable>::new(100 * entry_size + 1).capacity(),
+            100
+        );
+        assert!(!<TranspositionTable>::new(entry_size - 1).is_enabled());
+    }
+
+    #[test]
+    fn test_probe_store() {
+        let mut table = <TranspositionTable>::new(1024 * 1024);
+
+        assert_eq!(table.probe(7, 0), None);
+
+        table.store(7, 3, GameEvaluation::OnGoing(5), Bound::Lower, Some(2));
+
+        let entry = table.probe(7, 3).unwrap();
+        assert_eq!(entry.value, GameEvaluation::OnGoing(5));
+        assert_eq!(entry.bound, Bound::Lower);
+
+        // Shallower searches can use deeper results, but not the other way around
+        assert!(table.probe(7, 2).is_some());
+        assert_eq!(table.probe(7, 4), None);
+
+        // The best move is worth trying first however shallow the search was
+        assert_eq!(table.best_move(7), Some(2));
+        assert_eq!(table.best_move(8), None);
+
+        assert_eq!(table.hits(), 2);
+        assert_eq!(table.misses(), 2);
+
+        table.clear();
+        assert_eq!(table.probe(7, 0), None);
+        assert_eq!(table.hits(), 0);
+        assert_eq!(table.misses(), 1);
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut table = <TranspositionTable>::new(size_of::<Option<Entry<GameEvaluation>>>());
+
+        table.store(1, 5, GameEvaluation::Win(3), Bound::Exact, None);
+        table.store(2, 1, GameEvaluation::Draw, Bound::Upper, None);
+
+        assert_eq!(table.probe(1, 0), None);
+        assert_eq!(table.probe(2, 1).unwrap().value, GameEvaluation::Draw);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut table = <TranspositionTable>::new(0);
+
+        table.store(1, 5, GameEvaluation::Win(3), Bound::Exact, None);
+
+        assert_eq!(table.probe(1, 0), None);
+        assert_eq!(table.hits() + table.misses(), 0);
+    }
+}
@@ -1,251 +1,793 @@
-use core::panic;
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
+    cmp::Reverse,
     collections::HashMap,
-    fmt::{Debug, Display},
+    fmt::Debug,
     hash::Hash,
     marker::PhantomData,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-use crate::game::{GameBoard, GameEvaluation, MoM};
+use anyhow::bail;
 
-#[derive(Debug)]
-struct TreeNode<D: Debug + Clone> {
-    depth: usize,
-    is_edge: bool,
-    children: Vec<D>,
+use crate::{
+    game::{GameBoard, GameEvaluation, MoM},
+    mcts::{self, Mcts},
+    transposition::{self, Bound, TranspositionTable},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    MiniMax,
+    AlphaBeta,
+    /// Monte Carlo tree search, which plays random games out instead of
+    /// searching to a depth
+    Mcts,
 }
 
-impl<D: Clone + Debug> Display for TreeNode<D> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Depth: {}", self.depth)?;
-        writeln!(f, "IsEdge: {}", self.is_edge)?;
-        writeln!(f, "Children: {:?}", self.children)?;
-        Ok(())
+impl FromStr for Algorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alphabeta" => Ok(Algorithm::AlphaBeta),
+            "minimax" => Ok(Algorithm::MiniMax),
+            "mcts" => Ok(Algorithm::Mcts),
+            alg => bail!("Invalid alg type {}", alg),
+        }
     }
 }
 
-#[derive(Debug)]
-pub enum Algorithm {
-    MiniMax,
-    AlphaBeta,
+/// What a search found and how much work it took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchReport<D> {
+    pub alg: Algorithm,
+    /// The deepest search that finished, or for Monte Carlo searches how long
+    /// the most played line is
+    pub depth: usize,
+    pub eval: GameEvaluation,
+    /// The line of play the search expects, starting with its move
+    pub pv: Vec<D>,
+    /// How many times `evaluate` was called
+    pub nodes: usize,
+    /// The transposition table's hits and misses, if it has one
+    pub tt_stats: Option<(usize, usize)>,
+}
+
+impl<D> SearchReport<D> {
+    /// The evaluation once the searched move is played. The search was from
+    /// before the move, which brought a forced end a ply closer.
+    pub fn eval_after_move(&self) -> GameEvaluation {
+        self.eval.sub_plies(1)
+    }
 }
 
 #[derive(Debug)]
 pub struct Tree<B, D, E>
 where
-    D: Clone + Debug + Default,
-    E: Debug,
-    B: Hash + Eq + Clone + GameBoard<D, E>,
+    D: Clone + Debug + Default + Eq + Hash + Send + Sync,
+    E: Debug + Send,
+    B: Hash + Eq + Clone + GameBoard<D, E> + Send,
 {
-    init_position: B,
-    tree_node_map: HashMap<B, TreeNode<D>>,
-    walk_depth: usize,
+    depth: usize,
     eval_call_count: RefCell<usize>,
+    transposition_table: RefCell<TranspositionTable>,
+    deadline: Cell<Option<Instant>>,
+    /// Raised from outside to stop the search
+    stop_signal: Option<Arc<AtomicBool>>,
+    stopped: Cell<bool>,
+    horizon_reached: Cell<bool>,
+    last_report: RefCell<Option<SearchReport<D>>>,
+    /// Trees that search root moves in parallel, each on its own thread
+    workers: Vec<Mutex<Tree<B, D, E>>>,
+    /// How many playouts a Monte Carlo search runs without a time budget
+    iterations: usize,
+    /// Whether alpha-beta orders moves to prune more, or searches them in the
+    /// board's order
+    move_ordering: bool,
+    /// Up to two moves per ply that recently caused a cutoff there
+    killers: RefCell<Vec<[Option<D>; 2]>>,
+    /// How much each move has caused cutoffs, for the minimising and the
+    /// maximising player
+    history: RefCell<[HashMap<D, usize>; 2]>,
     alg: Algorithm,
     use_threats: bool,
-    ghost: PhantomData<E>,
+    ghost: PhantomData<(B, D, E)>,
 }
 
 impl<B, D, E> Tree<B, D, E>
 where
-    D: Clone + Debug + Default,
-    E: Debug,
-    B: Hash + Eq + Clone + GameBoard<D, E>,
+    D: Clone + Debug + Default + Eq + Hash + Send + Sync,
+    E: Debug + Send,
+    B: Hash + Eq + Clone + GameBoard<D, E> + Send,
 {
-    pub fn new(board: B, walk_depth: usize, alg: Algorithm, use_threats: bool) -> Self {
-        let depth = 0;
+    /// `depth` counts the current position, so a depth of 2 looks one move
+    /// ahead, and anything less than 1 is taken as 1. `tt_size` is how many
+    /// bytes the alpha-beta transposition table may use, 0 turns it off.
+    pub fn new(depth: usize, alg: Algorithm, use_threats: bool, tt_size: usize) -> Self {
+        Self {
+            depth: depth.max(1),
+            eval_call_count: RefCell::new(0),
+            transposition_table: RefCell::new(TranspositionTable::new(tt_size)),
+            deadline: Cell::new(None),
+            stop_signal: None,
+            stopped: Cell::new(false),
+            horizon_reached: Cell::new(false),
+            last_report: RefCell::new(None),
+            workers: vec![],
+            iterations: mcts::DEFAULT_ITERATIONS,
+            move_ordering: true,
+            killers: RefCell::new(vec![]),
+            history: RefCell::new([HashMap::new(), HashMap::new()]),
+            alg,
+            use_threats,
+            ghost: PhantomData,
+        }
+    }
 
-        let mut tree_node_map = HashMap::new();
+    /// Search the moves from the root on `threads` threads at once.
+    ///
+    /// Each thread gets its own share of the transposition table, and the
+    /// values found are the same as searching on one thread. Monte Carlo
+    /// searches always run on one thread.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        if threads <= 1 || self.alg == Algorithm::Mcts {
+            self.workers = vec![];
+            return self;
+        }
 
-        let moves = board.list_moves();
+        let tt_size = self.transposition_table.borrow().memory() / threads;
+        self.workers = (0..threads)
+            .map(|_| {
+                let mut worker = Tree::new(self.depth, self.alg, self.use_threats, tt_size);
+                worker.stop_signal = self.stop_signal.clone();
+                Mutex::new(worker)
+            })
+            .collect();
+        self.transposition_table = RefCell::new(TranspositionTable::new(0));
 
-        let root = TreeNode {
-            depth,
-            is_edge: true,
-            children: moves,
-        };
+        self
+    }
 
-        let init_position = board.clone();
+    /// How many playouts a Monte Carlo search runs when it isn't timed.
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
 
-        tree_node_map.insert(board, root);
+    /// Search alpha-beta's moves in the board's order without any heuristics,
+    /// to see how much they save.
+    pub fn with_move_ordering(mut self, move_ordering: bool) -> Self {
+        self.move_ordering = move_ordering;
+        for worker in &mut self.workers {
+            worker.get_mut().unwrap().move_ordering = move_ordering;
+        }
+        self
+    }
 
-        Self {
-            init_position,
-            tree_node_map,
-            walk_depth,
-            eval_call_count: RefCell::new(0),
-            alg,
-            use_threats,
-            ghost: PhantomData::default(),
+    /// Stop searching as soon as `signal` is raised, e.g. from another thread.
+    ///
+    /// `get_best_move_deepening` then plays the move from the deepest search
+    /// that finished, the other searches should only be stopped this way if
+    /// their result won't be used.
+    pub fn with_stop_signal(mut self, signal: Arc<AtomicBool>) -> Self {
+        for worker in &mut self.workers {
+            worker.get_mut().unwrap().stop_signal = Some(signal.clone());
         }
+        self.stop_signal = Some(signal);
+        self
+    }
+
+    /// How many times the last search called `evaluate`.
+    pub fn eval_call_count(&self) -> usize {
+        *self.eval_call_count.borrow()
     }
 
-    pub fn walk_start(&mut self, mut board: B) {
-        let node = self.tree_node_map.get(&board).unwrap();
-        let start_depth = node.depth;
+    /// What the last search for a move found, unless there was only one move
+    /// to play or no depth finished in time.
+    pub fn last_report(&self) -> Option<SearchReport<D>> {
+        self.last_report.borrow().clone()
+    }
+
+    pub fn get_best_move(&self, board: &mut B) -> D {
+        self.start_search();
+        self.last_report.replace(None);
+
+        let moves = board.list_moves();
 
-        self.walk_rec(&mut board, start_depth, 1);
+        if moves.len() == 1 {
+            return moves[0].clone();
+        }
+
+        let (eval, pv) = self.search(board, &[]);
+        let depth = match self.alg {
+            Algorithm::Mcts => pv.len(),
+            _ => self.depth,
+        };
+        let pv = self.complete_pv(board, pv, depth);
+        self.report(depth, eval, &pv);
+
+        pv[0].clone()
+    }
+
+    /// Search one depth deeper at a time until `budget` runs out.
+    ///
+    /// Each depth searches the previous depth's principal variation first,
+    /// and the move from the last depth to finish in time is returned.
+    pub fn get_best_move_timed(&mut self, board: &mut B, budget: Duration) -> D {
+        self.get_best_move_deepening(board, usize::MAX, Some(budget), |_| {})
     }
 
-    fn walk_rec(&mut self, board: &mut B, start_depth: usize, depth: usize) {
-        // Get the moves
+    /// Search one depth deeper at a time up to `max_depth`, until `budget`
+    /// runs out or the stop signal is raised, and return the move from the
+    /// last depth to finish.
+    ///
+    /// `on_depth` is told what each depth found as it finishes. A Monte Carlo
+    /// search doesn't go a depth at a time, it runs its playouts and reports
+    /// once.
+    pub fn get_best_move_deepening(
+        &mut self,
+        board: &mut B,
+        max_depth: usize,
+        budget: Option<Duration>,
+        mut on_depth: impl FnMut(&SearchReport<D>),
+    ) -> D {
+        self.start_search();
+        self.last_report.replace(None);
+
         let moves = board.list_moves();
 
-        // Insert the board if needed
-        let node = self.tree_node_map.entry(board.clone()).or_insert(TreeNode {
-            depth: start_depth + depth,
-            is_edge: false,
-            children: moves.clone(),
-        });
+        if moves.len() == 1 {
+            return moves[0].clone();
+        }
 
-        // If at depth then we are done
-        if depth >= self.walk_depth {
-            node.is_edge = true;
-            return;
+        self.deadline
+            .set(budget.map(|budget| Instant::now() + budget));
+        self.stopped.set(false);
+
+        // Playouts don't go a depth at a time, they just run until time is up
+        if self.alg == Algorithm::Mcts {
+            let (eval, pv) = self.search(board, &[]);
+            self.deadline.set(None);
+            self.stopped.set(false);
+
+            let Some(best) = pv.first().cloned() else {
+                return moves[0].clone();
+            };
+            self.report(pv.len(), eval, &pv);
+            on_depth(self.last_report.borrow().as_ref().unwrap());
+
+            return best;
         }
 
-        node.is_edge = false;
+        // A depth of one would only look at the root, so start below it
+        let mut depth = max_depth.min(2);
+        let mut completed: Option<(usize, GameEvaluation, Vec<D>)> = None;
+
+        while depth <= max_depth {
+            self.depth = depth;
+            self.horizon_reached.set(false);
+
+            let pv = completed.as_ref().map_or(&[][..], |(_, _, pv)| pv);
+            let (eval, pv) = self.search(board, pv);
+            if self.out_of_time() {
+                break;
+            }
+
+            let pv = self.complete_pv(board, pv, depth);
+            self.report(depth, eval, &pv);
+            on_depth(self.last_report.borrow().as_ref().unwrap());
+            completed = Some((depth, eval, pv));
 
-        // For each move
-        for move_data in moves {
-            // * apply move
-            board
-                .apply_move(&move_data)
-                .expect("This should never fail as it is only valid moves");
-            // * recurse
-            self.walk_rec(board, start_depth, depth + 1);
-            // * remove move
-            board
-                .remove_move(&move_data)
-                .expect("This should never fail as it is only valid moves");
+            // Deeper searches can't change a forced result or a tree that
+            // already reaches the end of every game.
+            if eval.is_terminal() || !self.horizon_reached.get() {
+                break;
+            }
+
+            depth += 1;
         }
+
+        self.deadline.set(None);
+        self.stopped.set(false);
+
+        let Some((depth, eval, pv)) = completed else {
+            return moves[0].clone();
+        };
+
+        // Report again so the counts include the depth that was cut short
+        self.report(depth, eval, &pv);
+
+        pv[0].clone()
     }
 
-    pub fn print_from_node(&self, board: &mut B) {
-        let Some(node) = self.tree_node_map.get(&board) else {
-            return;
+    /// Finish a line cut short by the transposition table, following the best
+    /// moves stored for the positions past its end until it is `depth` deep.
+    fn complete_pv(&self, board: &B, mut pv: Vec<D>, depth: usize) -> Vec<D> {
+        let mut board = board.clone();
+        for m in &pv {
+            board.apply_move(m).unwrap();
+        }
+
+        while pv.len() + 1 < depth && !board.is_over() {
+            let Some(index) = self.stored_best_move(transposition::key(&board)) else {
+                break;
+            };
+            let moves = match self.move_ordering {
+                true => board.ordered_moves(),
+                false => board.list_moves(),
+            };
+            let Some(m) = moves.get(usize::from(index)).cloned() else {
+                break;
+            };
+
+            board.apply_move(&m).unwrap();
+            pv.push(m);
+        }
+
+        pv
+    }
+
+    /// The best move stored for a position in this tree's table or, when
+    /// searching in parallel, any worker's.
+    fn stored_best_move(&self, key: u64) -> Option<u8> {
+        self.transposition_table
+            .borrow()
+            .best_move(key)
+            .or_else(|| {
+                self.workers.iter().find_map(|worker| {
+                    worker
+                        .lock()
+                        .unwrap()
+                        .transposition_table
+                        .borrow()
+                        .best_move(key)
+                })
+            })
+    }
+
+    fn report(&self, depth: usize, eval: GameEvaluation, pv: &[D]) {
+        let tt_enabled = match self.workers.first() {
+            Some(worker) => worker
+                .lock()
+                .unwrap()
+                .transposition_table
+                .borrow()
+                .is_enabled(),
+            None => self.transposition_table.borrow().is_enabled(),
         };
+        let tt_stats = tt_enabled.then(|| self.tt_stats());
+
+        self.last_report.replace(Some(SearchReport {
+            alg: self.alg,
+            depth,
+            eval,
+            pv: pv.to_vec(),
+            nodes: self.eval_call_count(),
+            tt_stats,
+        }));
+    }
 
-        // TODO(ajone239): add in indent prints
-        // println!("{:indent$}Indented text!", "", indent=indent);
-        println!("Board:");
-        println!("{}", board);
-        println!("Node:");
-        println!("{}", node);
-
-        // For each move
-        for move_data in &node.children {
-            // * apply move
-            board
-                .apply_move(&move_data)
-                .expect("This should never fail as it is only valid moves");
-            // * recurse
-            self.print_from_node(board);
-            // * remove move
-            board
-                .remove_move(&move_data)
-                .expect("This should never fail as it is only valid moves");
-        }
-    }
-
-    fn fmt_rec(&self, f: &mut std::fmt::Formatter<'_>, board: &mut B) -> std::fmt::Result {
-        let Some(node) = self.tree_node_map.get(&board) else {
-            return Ok(());
+    /// Every legal move with its evaluation and the line of play expected
+    /// after it, best first for whoever is to play.
+    ///
+    /// Unlike a search for the best move, every move gets its exact value.
+    pub fn analyze(&self, board: &mut B) -> Vec<(D, GameEvaluation, Vec<D>)> {
+        self.start_search();
+
+        if self.alg == Algorithm::Mcts {
+            return self.monte_carlo(board).ranked_lines();
+        }
+
+        let moves = match self.move_ordering {
+            true => board.ordered_moves(),
+            false => board.list_moves(),
         };
 
-        // TODO(ajone239): add in indent prints
-        // println!("{:indent$}Indented text!", "", indent=indent);
-        writeln!(f, "Board:")?;
-        writeln!(f, "{}", board)?;
-        writeln!(f, "Node:")?;
-        writeln!(f, "{}", node)?;
+        let mut lines: Vec<(D, GameEvaluation, Vec<D>)> = match self.workers.is_empty() {
+            true => moves.iter().map(|m| self.analyze_move(board, m)).collect(),
+            false => self.parallel_analyze(board, &moves),
+        };
 
-        // For each move
-        for move_data in &node.children {
-            // * apply move
-            board
-                .apply_move(&move_data)
-                .expect("This should never fail as it is only valid moves");
-            // * recurse
-            self.fmt_rec(f, board)?;
-            // * remove move
-            board
-                .remove_move(&move_data)
-                .expect("This should never fail as it is only valid moves");
+        match board.min_or_maxing() {
+            MoM::Max => lines.sort_by_key(|&(_, eval, _)| Reverse(eval)),
+            MoM::Min => lines.sort_by_key(|&(_, eval, _)| eval),
         }
 
-        Ok(())
+        lines
     }
 
-    pub fn get_best_move(&self, board: &mut B) -> D {
-        *self.eval_call_count.borrow_mut() = 0;
+    /// The exact evaluation of playing `m` and the line of play expected from
+    /// there, starting with `m`.
+    fn analyze_move(&self, board: &mut B, m: &D) -> (D, GameEvaluation, Vec<D>) {
+        let (eval, mut line) = Self::apply_recurse_remove(board, m, |board, _| match self.alg {
+            Algorithm::AlphaBeta => self.alpha_beta_minimax(
+                board,
+                1,
+                &[],
+                GameEvaluation::Lose(0),
+                GameEvaluation::Win(0),
+            ),
+            _ => self.minimax(board, 1, &[]),
+        });
+        line.insert(0, m.clone());
 
-        let moves = board.list_moves();
+        (m.clone(), eval, self.complete_pv(board, line, self.depth))
+    }
 
-        if moves.len() == 1 {
-            return moves[0].clone();
-        }
+    /// Analyze the moves on the workers, each taking the next move left.
+    fn parallel_analyze(&self, board: &B, moves: &[D]) -> Vec<(D, GameEvaluation, Vec<D>)> {
+        let next = AtomicUsize::new(0);
+        let lines = Mutex::new(vec![]);
+
+        let depth = self.depth;
+        let deadline = self.deadline.get();
+        let (next, lines_ref) = (&next, &lines);
 
-        let (_, move_data) = match self.alg {
-            Algorithm::MiniMax => self.minimax(board, None),
-            Algorithm::AlphaBeta => {
-                self.alpha_beta_minimax(board, None, GameEvaluation::Lose, GameEvaluation::Win)
+        thread::scope(|scope| {
+            for worker in &self.workers {
+                let mut board = board.clone();
+
+                scope.spawn(move || {
+                    let mut worker = worker.lock().unwrap();
+                    worker.prepare(depth, deadline);
+
+                    while let Some(m) = moves.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let line = worker.analyze_move(&mut board, m);
+                        lines_ref.lock().unwrap().push(line);
+                    }
+                });
             }
-        };
+        });
 
-        let val = *self.eval_call_count.borrow();
+        self.gather_workers();
 
-        println!("Evaluated {} times with {:?}", val, self.alg);
+        // Back in the board's order so ties rank the same as on one thread
+        let mut lines = lines.into_inner().unwrap();
+        lines.sort_by_key(|(m, _, _)| moves.iter().position(|o| o == m));
+        lines
+    }
 
-        move_data
+    fn search(&self, board: &mut B, pv: &[D]) -> (GameEvaluation, Vec<D>) {
+        match self.alg {
+            Algorithm::Mcts => {
+                let search = self.monte_carlo(board);
+                (search.eval(), search.pv())
+            }
+            _ if !self.workers.is_empty() => self.parallel_search(board, pv),
+            Algorithm::MiniMax => self.minimax(board, 0, pv),
+            Algorithm::AlphaBeta => self.alpha_beta_minimax(
+                board,
+                0,
+                pv,
+                GameEvaluation::Lose(0),
+                GameEvaluation::Win(0),
+            ),
+        }
     }
 
-    fn minimax(&self, board: &mut B, move_to_get_here: Option<D>) -> (GameEvaluation, D) {
-        let Some(node) = self.tree_node_map.get(&board) else {
-            panic!("Attempted to use an unwalked board!");
+    /// Run playouts until the deadline, or for `iterations` if there isn't
+    /// one.
+    fn monte_carlo(&self, board: &B) -> Mcts<D> {
+        let iterations = match self.deadline.get() {
+            Some(_) => usize::MAX,
+            None => self.iterations,
         };
 
-        // Return the nodes eval if it is terminal
-        if node.is_edge {
-            if move_to_get_here.is_none() {
-                println!("{board}");
+        let mut search = Mcts::new(board);
+        search.run(
+            board,
+            iterations,
+            || self.out_of_time(),
+            &mut rand::thread_rng(),
+        );
+        *self.eval_call_count.borrow_mut() += search.eval_call_count();
+
+        search
+    }
+
+    /// Split the root's children between the workers.
+    ///
+    /// Each worker takes the next unsearched child and searches it with the
+    /// best value found so far as its bound, so a child only comes back exact
+    /// if it beats every child finished before it, just like searching them
+    /// one after the other. Children of equal value may finish in any order,
+    /// so which of them is played can change from run to run.
+    fn parallel_search(&self, board: &B, pv: &[D]) -> (GameEvaluation, Vec<D>) {
+        let moves = match self.move_ordering {
+            true => board.ordered_moves(),
+            false => board.list_moves(),
+        };
+        let children: Vec<(&D, &[D])> = Self::pv_first(&moves, pv).collect();
+
+        let maxing = matches!(board.min_or_maxing(), MoM::Max);
+        let next = AtomicUsize::new(0);
+        let best: Mutex<Option<(GameEvaluation, Vec<D>)>> = Mutex::new(None);
+
+        let depth = self.depth;
+        let deadline = self.deadline.get();
+        let (children, next, best_ref) = (&children, &next, &best);
+
+        thread::scope(|scope| {
+            for worker in &self.workers {
+                let mut board = board.clone();
+
+                scope.spawn(move || {
+                    let mut worker = worker.lock().unwrap();
+                    worker.prepare(depth, deadline);
+
+                    while let Some(&(m, pv)) = children.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let bound = best_ref.lock().unwrap().as_ref().map(|(eval, _)| *eval);
+                        // No game can end before the root's move
+                        let (lose, win) = (GameEvaluation::Lose(1), GameEvaluation::Win(1));
+                        let (alpha, beta) = match (maxing, bound) {
+                            (true, Some(bound)) => (bound.max(lose), win),
+                            (false, Some(bound)) => (lose, bound.min(win)),
+                            (_, None) => (lose, win),
+                        };
+
+                        // A win with the root's move has already been found
+                        if beta <= alpha {
+                            break;
+                        }
+
+                        let (eval, mut line) =
+                            Self::apply_recurse_remove(&mut board, m, |board, _| {
+                                match worker.alg {
+                                    Algorithm::AlphaBeta => {
+                                        worker.alpha_beta_minimax(board, 1, pv, alpha, beta)
+                                    }
+                                    _ => worker.minimax(board, 1, pv),
+                                }
+                            });
+
+                        if worker.stopped.get() {
+                            break;
+                        }
+                        line.insert(0, m.clone());
+
+                        let mut best = best_ref.lock().unwrap();
+                        let better = match &*best {
+                            None => true,
+                            Some((best_eval, _)) if maxing => eval > *best_eval,
+                            Some((best_eval, _)) => eval < *best_eval,
+                        };
+                        if better {
+                            *best = Some((eval, line));
+                        }
+                    }
+                });
+            }
+        });
+
+        self.gather_workers();
+
+        best.into_inner()
+            .unwrap()
+            .unwrap_or((GameEvaluation::Draw, vec![]))
+    }
+
+    /// Get a worker ready to search to `depth` before `deadline`.
+    fn prepare(&mut self, depth: usize, deadline: Option<Instant>) {
+        self.depth = depth;
+        self.deadline.set(deadline);
+        self.stopped.set(false);
+        self.horizon_reached.set(false);
+        *self.eval_call_count.borrow_mut() = 0;
+    }
+
+    /// Count the workers' evaluations as this tree's, and pass on whether any
+    /// reached the horizon or ran out of time.
+    fn gather_workers(&self) {
+        for worker in &self.workers {
+            let worker = worker.lock().unwrap();
+            *self.eval_call_count.borrow_mut() += worker.eval_call_count();
+            if worker.horizon_reached.get() {
+                self.horizon_reached.set(true);
             }
-            let move_data = move_to_get_here.expect("Trying to get move for a terminal position!");
-            let eval = board.evaluate(self.use_threats);
-            *self.eval_call_count.borrow_mut() += 1;
-            return (eval, move_data);
+            if worker.stopped.get() {
+                self.stopped.set(true);
+            }
+        }
+    }
+
+    /// Start counting evaluations and table hits from zero, and forget which
+    /// moves caused cutoffs in the last search.
+    fn start_search(&self) {
+        *self.eval_call_count.borrow_mut() = 0;
+        self.transposition_table.borrow_mut().reset_stats();
+        self.killers.borrow_mut().clear();
+        self.history
+            .borrow_mut()
+            .iter_mut()
+            .for_each(HashMap::clear);
+
+        for worker in &self.workers {
+            worker.lock().unwrap().start_search();
+        }
+    }
+
+    /// The transposition table's hits and misses since the last search started.
+    pub fn tt_stats(&self) -> (usize, usize) {
+        if !self.workers.is_empty() {
+            return self.workers.iter().fold((0, 0), |(hits, misses), worker| {
+                let (h, m) = worker.lock().unwrap().tt_stats();
+                (hits + h, misses + m)
+            });
+        }
+
+        let table = self.transposition_table.borrow();
+        (table.hits(), table.misses())
+    }
+
+    /// Check the clock and the stop signal, and remember if either says to
+    /// stop so the whole search can unwind.
+    fn out_of_time(&self) -> bool {
+        if self.stopped.get() {
+            return true;
+        }
+
+        let signalled = self
+            .stop_signal
+            .as_ref()
+            .is_some_and(|signal| signal.load(Ordering::Relaxed));
+        let expired = self
+            .deadline
+            .get()
+            .is_some_and(|deadline| Instant::now() >= deadline);
+
+        if signalled || expired {
+            self.stopped.set(true);
+        }
+
+        self.stopped.get()
+    }
+
+    /// Whether a node `ply` moves from the root is as deep as the search goes.
+    /// The root always gets searched as it has to come up with a move.
+    fn is_edge(&self, ply: usize) -> bool {
+        ply > 0 && ply + 1 >= self.depth
+    }
+
+    /// Evaluate a leaf `ply` moves from the root and note whether there could
+    /// be more game past it.
+    fn evaluate_edge(&self, board: &B, ply: usize) -> GameEvaluation {
+        let eval = board.evaluate(self.use_threats).add_plies(ply);
+        *self.eval_call_count.borrow_mut() += 1;
+
+        if !eval.is_terminal() {
+            self.horizon_reached.set(true);
+        }
+
+        eval
+    }
+
+    /// A value the transposition table answered for a node. Unless it's a
+    /// forced result, the game carries on past the edge of the search that
+    /// stored it, so deeper searches can still find more.
+    fn table_cutoff(&self, value: GameEvaluation) -> GameEvaluation {
+        if !value.is_terminal() {
+            self.horizon_reached.set(true);
+        }
+
+        value
+    }
+
+    /// The children of a node with the principal variation's move first.
+    ///
+    /// Each child is paired with the part of the principal variation to follow
+    /// below it, which is empty for everything but the first.
+    fn pv_first<'a>(children: &'a [D], pv: &'a [D]) -> impl Iterator<Item = (&'a D, &'a [D])> {
+        let first = pv.first().filter(|m| children.contains(m));
+
+        first.into_iter().map(|m| (m, &pv[1..])).chain(
+            children
+                .iter()
+                .filter(move |m| Some(*m) != first)
+                .map(|m| (m, &[][..])),
+        )
+    }
+
+    /// The moves from `board` in the order alpha-beta searches them, each with
+    /// its place in the board's own order.
+    ///
+    /// The transposition table's best move comes first, then this ply's killer
+    /// moves, then the rest by how often they caused cutoffs, ties keeping the
+    /// board's order.
+    fn order_moves(&self, board: &B, ply: usize, key: u64) -> (Vec<usize>, Vec<D>) {
+        if !self.move_ordering {
+            let moves = board.list_moves();
+            return ((0..moves.len()).collect(), moves);
+        }
+
+        let tt_move = self
+            .transposition_table
+            .borrow()
+            .best_move(key)
+            .map(usize::from);
+        let killers = self.killers.borrow();
+        let killers = killers.get(ply);
+        let history = self.history.borrow();
+        let history = &history[matches!(board.min_or_maxing(), MoM::Max) as usize];
+
+        let mut moves: Vec<(usize, D)> = board.ordered_moves().into_iter().enumerate().collect();
+        moves.sort_by_key(|(i, m)| {
+            let tier = if Some(*i) == tt_move {
+                0
+            } else if killers.is_some_and(|k| k.iter().flatten().any(|k| k == m)) {
+                1
+            } else {
+                2
+            };
+
+            (tier, Reverse(history.get(m).copied().unwrap_or(0)))
+        });
+
+        moves.into_iter().unzip()
+    }
+
+    /// Remember a move that caused a cutoff `depth` moves from the edge of the
+    /// tree, so it gets tried early in other positions.
+    fn note_cutoff(&self, m: &D, ply: usize, depth: usize, maxing: bool) {
+        if !self.move_ordering {
+            return;
+        }
+
+        let mut killers = self.killers.borrow_mut();
+        if killers.len() <= ply {
+            killers.resize(ply + 1, [None, None]);
+        }
+        let killers = &mut killers[ply];
+        if killers[0].as_ref() != Some(m) {
+            killers[1] = killers[0].replace(m.clone());
+        }
+
+        // Cutoffs nearer the root save more work
+        *self.history.borrow_mut()[maxing as usize]
+            .entry(m.clone())
+            .or_default() += (depth + 1) * (depth + 1);
+    }
+
+    /// Returns the evaluation of `board` and the best line of play from it.
+    ///
+    /// `ply` is how many moves `board` is from the root of the search.
+    fn minimax(&self, board: &mut B, ply: usize, pv: &[D]) -> (GameEvaluation, Vec<D>) {
+        // Return the nodes eval if it is terminal
+        if self.is_edge(ply) || (ply > 0 && board.is_over()) {
+            return (self.evaluate_edge(board, ply), vec![]);
+        }
+
+        if self.out_of_time() {
+            return (GameEvaluation::Draw, vec![]);
         }
 
         // Run minimax on all the children
+        let children = board.list_moves();
+
         let mut evals = vec![];
-        for m in &node.children {
+        for (m, pv) in Self::pv_first(&children, pv) {
             // In the recursion call minimax again and push the result to a local evals vector
             let f = |board: &mut _, move_data: &D| {
-                evals.push(self.minimax(board, Some(move_data.clone())));
+                let (eval, mut line) = self.minimax(board, ply + 1, pv);
+                line.insert(0, move_data.clone());
+                evals.push((eval, line));
             };
 
             // Recurse Recurse
             Tree::apply_recurse_remove(board, m, f);
         }
 
-        // Get the best eval for us
-        // and get the move associated as we _might_ need it
-        let (eval, move_data) = match board.min_or_maxing() {
+        // Get the best eval for us along with the line that gets there
+        match board.min_or_maxing() {
             MoM::Max => evals.into_iter().max_by(|x, y| x.0.cmp(&y.0)).unwrap(),
             MoM::Min => evals.into_iter().min_by(|x, y| x.0.cmp(&y.0)).unwrap(),
-        };
-
-        // If `move_to_get_here` is None then we know that `get_best_move()` called it
-        // so we know to use the move that we found not that we were told.
-        //
-        // Otherwise use the move that we were told as it is correct.
-        let ret_move = match move_to_get_here {
-            Some(m) => m,
-            None => move_data,
-        };
-
-        (eval, ret_move)
+        }
     }
 
     fn apply_recurse_remove<T, F>(board: &mut B, move_data: &D, f: F) -> T
@@ -254,114 +796,470 @@ where
     {
         // * apply move
         board
-            .apply_move(&move_data)
+            .apply_move(move_data)
             .expect("This should never fail as it is only valid moves");
         // * recurse
         let result = f(board, move_data);
         // * remove move
         board
-            .remove_move(&move_data)
+            .remove_move(move_data)
             .expect("This should never fail as it is only valid moves");
 
         result
     }
 
+    /// Returns the evaluation of `board` and the best line of play from it.
+    ///
+    /// `ply` is how many moves `board` is from the root of the search.
     fn alpha_beta_minimax(
         &self,
         board: &mut B,
-        move_to_get_here: Option<D>,
+        ply: usize,
+        pv: &[D],
         mut alpha: GameEvaluation,
         mut beta: GameEvaluation,
-    ) -> (GameEvaluation, D) {
-        // Grab the node
-        let Some(node) = self.tree_node_map.get(&board) else {
-            println!("{}", board);
-            panic!("Attempted to use an unwalked board!");
+    ) -> (GameEvaluation, Vec<D>) {
+        // Return the nodes eval if it is terminal
+        if self.is_edge(ply) || (ply > 0 && board.is_over()) {
+            return (self.evaluate_edge(board, ply), vec![]);
+        }
+
+        if self.out_of_time() {
+            return (GameEvaluation::Draw, vec![]);
+        }
+
+        // Nothing from here can end the game sooner than the next move
+        alpha = alpha.max(GameEvaluation::Lose(ply + 1));
+        beta = beta.min(GameEvaluation::Win(ply + 1));
+        if beta <= alpha {
+            return (alpha, vec![]);
+        }
+
+        // How far this node is from the edge of the tree
+        let depth = self.depth.saturating_sub(ply + 1);
+        let key = transposition::key(board);
+
+        // Skip positions we've already searched deep enough. The root always
+        // gets searched as it has to come up with a move.
+        let entry = match ply {
+            0 => None,
+            _ => self.transposition_table.borrow_mut().probe(key, depth),
         };
+        if let Some(mut entry) = entry {
+            // The table counts plies from the stored position, not the root
+            entry.value = entry.value.add_plies(ply);
 
-        // Return the nodes eval if it is terminal
-        if node.is_edge {
-            if move_to_get_here.is_none() {
-                println!("{board}");
+            match entry.bound {
+                Bound::Exact => return (self.table_cutoff(entry.value), vec![]),
+                Bound::Lower => alpha = GameEvaluation::max(alpha, entry.value),
+                Bound::Upper => beta = GameEvaluation::min(beta, entry.value),
+            }
+
+            if beta <= alpha {
+                return (self.table_cutoff(entry.value), vec![]);
             }
-            let move_data = move_to_get_here.expect("Trying to get move for a terminal position!");
-            let eval = board.evaluate(self.use_threats);
-            *self.eval_call_count.borrow_mut() += 1;
-            return (eval, move_data);
         }
 
-        let (eval, move_data) = match board.min_or_maxing() {
+        let (window_alpha, window_beta) = (alpha, beta);
+
+        let (order, children) = self.order_moves(board, ply, key);
+        let mut line = vec![];
+
+        let eval = match board.min_or_maxing() {
             MoM::Max => {
                 // Min val for max
                 let mut eval: GameEvaluation = GameEvaluation::MinusInfinity;
-                let mut move_data: D = Default::default();
 
-                for m in node.children.iter() {
+                for (m, pv) in Self::pv_first(&children, pv) {
                     board.apply_move(m).unwrap();
-                    let (temp_eval, temp_move_data) =
-                        self.alpha_beta_minimax(board, Some(m.clone()), alpha, beta);
+                    let (temp_eval, temp_line) =
+                        self.alpha_beta_minimax(board, ply + 1, pv, alpha, beta);
                     board.remove_move(m).unwrap();
 
                     if temp_eval > eval {
                         eval = temp_eval;
-                        move_data = temp_move_data;
+                        line = temp_line;
+                        line.insert(0, m.clone());
                     }
 
                     alpha = GameEvaluation::max(alpha, eval);
 
                     if beta <= alpha {
+                        self.note_cutoff(m, ply, depth, true);
                         break;
                     }
                 }
-                (eval, move_data)
+                eval
             }
             MoM::Min => {
                 // Max val for min
                 let mut eval: GameEvaluation = GameEvaluation::PlusInfinity;
-                let mut move_data: D = Default::default();
 
-                for m in node.children.iter() {
+                for (m, pv) in Self::pv_first(&children, pv) {
                     board.apply_move(m).unwrap();
-                    let (temp_eval, temp_move_data) =
-                        self.alpha_beta_minimax(board, Some(m.clone()), alpha, beta);
+                    let (temp_eval, temp_line) =
+                        self.alpha_beta_minimax(board, ply + 1, pv, alpha, beta);
                     board.remove_move(m).unwrap();
 
                     if temp_eval < eval {
                         eval = temp_eval;
-                        move_data = temp_move_data;
+                        line = temp_line;
+                        line.insert(0, m.clone());
                     }
                     beta = GameEvaluation::min(beta, eval);
 
                     if beta <= alpha {
+                        self.note_cutoff(m, ply, depth, false);
                         break;
                     }
                 }
-                (eval, move_data)
+                eval
             }
         };
 
-        // If `move_to_get_here` is None then we know that `get_best_move()` called it
-        // so we know to use the move that we found not that we were told.
-        //
-        // Otherwise use the move that we were told as it is correct.
-        let ret_move = match move_to_get_here {
-            Some(m) => m,
-            None => move_data,
-        };
+        // A search cut short by the clock didn't see enough to be remembered
+        if !self.stopped.get() {
+            let bound = if eval <= window_alpha {
+                Bound::Upper
+            } else if eval >= window_beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+
+            let best_move = line
+                .first()
+                .and_then(|best| children.iter().position(|m| m == best))
+                .map(|i| order[i] as u8);
+
+            self.transposition_table.borrow_mut().store(
+                key,
+                depth,
+                eval.sub_plies(ply),
+                bound,
+                best_move,
+            );
+        }
 
-        (eval, ret_move)
+        (eval, line)
     }
 }
 
-impl<B, D, E> Display for Tree<B, D, E>
-where
-    D: Clone + Debug + Default,
-    E: Debug,
-    B: Hash + Eq + Clone + GameBoard<D, E>,
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut board = self.init_position.clone();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::{
+            board::{Board, BoardError},
+            board_move::BoardMove,
+        },
+        game::{Evaluate, MovePiece},
+    };
+    use rstest::rstest;
+
+    #[test]
+    fn test_timed_finds_win() {
+        let mut board = <Board>::from_columns(&[0, 0, 1, 1, 2, 2]);
+
+        let mut tree: Tree<Board, BoardMove, BoardError> =
+            Tree::new(1, Algorithm::AlphaBeta, false, 0);
+
+        let best = tree.get_best_move_timed(&mut board, Duration::from_secs(5));
+
+        assert_eq!(best.column, 3);
+    }
+
+    #[rstest]
+    #[case(Algorithm::MiniMax)]
+    #[case(Algorithm::AlphaBeta)]
+    fn test_depth_zero(#[case] alg: Algorithm) {
+        let mut board = <Board>::from_columns(&[0, 0, 1, 1, 2, 2]);
+
+        let tree: Tree<Board, BoardMove, BoardError> = Tree::new(0, alg, false, 0);
+
+        let best = tree.get_best_move(&mut board);
+
+        assert!(board.is_move_valid(&best));
+    }
+
+    #[test]
+    fn test_fixed_depth_after_timed() {
+        let mut board = <Board>::from_columns(&[3, 3, 2, 4]);
+
+        let fixed: Tree<Board, BoardMove, BoardError> =
+            Tree::new(4, Algorithm::AlphaBeta, false, 0);
+        let (fixed_eval, _) = fixed.search(&mut board, &[]);
+
+        // The timed search must leave the tree ready for a fixed depth one
+        let mut timed: Tree<Board, BoardMove, BoardError> =
+            Tree::new(1, Algorithm::AlphaBeta, false, 0);
+        timed.get_best_move_timed(&mut board, Duration::from_millis(200));
+        timed.depth = 4;
+        let (timed_eval, _) = timed.search(&mut board, &[]);
+
+        assert_eq!(fixed_eval, timed_eval);
+    }
+
+    #[rstest]
+    #[case(Algorithm::MiniMax)]
+    #[case(Algorithm::AlphaBeta)]
+    fn test_analyze(#[case] alg: Algorithm) {
+        let mut board = <Board>::from_columns(&[0, 0, 1, 1, 2, 2]);
+
+        let tree: Tree<Board, BoardMove, BoardError> = Tree::new(4, alg, false, 1024 * 1024);
+
+        let lines = tree.analyze(&mut board);
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0].0.column, 3);
+        assert_eq!(lines[0].1, GameEvaluation::Win(1));
+        assert!(lines.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert!(lines.iter().all(|(m, _, pv)| pv[0] == *m));
+        assert_eq!(board, <Board>::from_columns(&[0, 0, 1, 1, 2, 2]));
+    }
+
+    #[rstest]
+    #[case(&[])]
+    #[case(&[3, 3, 2, 4])]
+    #[case(&[3, 2, 3, 2, 4, 4])]
+    fn test_analyze_matches_search(#[case] moves: &[usize]) {
+        let mut board = <Board>::from_columns(moves);
+
+        let tree: Tree<Board, BoardMove, BoardError> =
+            Tree::new(6, Algorithm::AlphaBeta, false, 1024 * 1024);
+        let lines = tree.analyze(&mut board);
+
+        assert_eq!(lines.len(), board.list_moves().len());
+        assert_eq!(lines[0].1, tree.search(&mut board, &[]).0);
+
+        // Each move's value is exact, whichever thread analyzed it
+        let threaded: Tree<Board, BoardMove, BoardError> =
+            Tree::new(6, Algorithm::AlphaBeta, false, 1024 * 1024).with_threads(3);
+        let evals = |lines: &[(BoardMove, GameEvaluation, Vec<BoardMove>)]| {
+            lines
+                .iter()
+                .map(|(m, eval, _)| (m.column, *eval))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(evals(&threaded.analyze(&mut board)), evals(&lines));
+    }
+
+    #[test]
+    fn test_timed_without_time() {
+        let mut board = <Board>::from_columns(&[3, 3]);
+
+        let mut tree: Tree<Board, BoardMove, BoardError> =
+            Tree::new(1, Algorithm::MiniMax, false, 0);
+
+        let best = tree.get_best_move_timed(&mut board, Duration::ZERO);
+
+        assert!(board.is_move_valid(&best));
+    }
+
+    #[test]
+    fn test_transposition_table_matches() {
+        let mut board = <Board>::from_columns(&[3, 3, 2, 4, 4, 2]);
+
+        let plain: Tree<Board, BoardMove, BoardError> =
+            Tree::new(7, Algorithm::AlphaBeta, false, 0);
+        let (plain_eval, _) = plain.search(&mut board, &[]);
+
+        let cached: Tree<Board, BoardMove, BoardError> =
+            Tree::new(7, Algorithm::AlphaBeta, false, 1024 * 1024);
+        let (cached_eval, _) = cached.search(&mut board, &[]);
+
+        let (hits, misses) = cached.tt_stats();
+
+        assert_eq!(plain_eval, cached_eval);
+        assert!(hits > 0);
+        assert!(misses > 0);
+        assert!(cached.eval_call_count() < plain.eval_call_count());
+    }
+
+    #[rstest]
+    #[case(&[], 6, Algorithm::MiniMax, 4, 16807)]
+    #[case(&[], 7, Algorithm::AlphaBeta, 6, 16596)]
+    #[case(&[3, 3, 2, 4], 6, Algorithm::MiniMax, 3, 16230)]
+    #[case(&[3, 3, 2, 4], 7, Algorithm::AlphaBeta, 4, 9057)]
+    #[case(&[3, 3, 2, 4, 4, 2, 1, 5, 5, 3], 7, Algorithm::AlphaBeta, 0, 1)]
+    fn test_matches_walked_tree(
+        #[case] moves: &[usize],
+        #[case] depth: usize,
+        #[case] alg: Algorithm,
+        #[case] expected_column: usize,
+        #[case] expected_evals: usize,
+    ) {
+        // These are what the search found back when it walked the whole tree
+        // up front, in the board's order, less the evaluations past the end of
+        // a game that it no longer makes.
+        let mut board = <Board>::from_columns(moves);
+
+        let tree: Tree<Board, BoardMove, BoardError> =
+            Tree::new(depth, alg, false, 0).with_move_ordering(false);
+
+        assert_eq!(tree.get_best_move(&mut board).column, expected_column);
+        assert_eq!(tree.eval_call_count(), expected_evals);
+    }
+
+    #[rstest]
+    #[case(&[], 6, Algorithm::MiniMax)]
+    #[case(&[], 7, Algorithm::AlphaBeta)]
+    #[case(&[3, 3, 2, 4], 7, Algorithm::AlphaBeta)]
+    #[case(&[3, 3, 2, 4, 4, 2], 8, Algorithm::AlphaBeta)]
+    #[case(&[0, 0, 1, 1, 2, 2], 5, Algorithm::AlphaBeta)]
+    fn test_threads_match_sequential(
+        #[case] moves: &[usize],
+        #[case] depth: usize,
+        #[case] alg: Algorithm,
+    ) {
+        let mut board = <Board>::from_columns(moves);
+
+        let sequential: Tree<Board, BoardMove, BoardError> =
+            Tree::new(depth, alg, false, 1024 * 1024);
+        let (sequential_eval, _) = sequential.search(&mut board, &[]);
+
+        for threads in [2, 4] {
+            let parallel: Tree<Board, BoardMove, BoardError> =
+                Tree::new(depth, alg, false, 1024 * 1024).with_threads(threads);
+            let (parallel_eval, pv) = parallel.search(&mut board, &[]);
+
+            assert_eq!(parallel_eval, sequential_eval);
+            assert!(board.is_move_valid(&pv[0]));
+            assert!(parallel.eval_call_count() > 0);
+        }
+        assert_eq!(board, <Board>::from_columns(moves));
+    }
+
+    #[test]
+    fn test_threads_timed() {
+        let mut board = <Board>::from_columns(&[0, 0, 1, 1, 2, 2]);
+
+        let mut tree: Tree<Board, BoardMove, BoardError> =
+            Tree::new(1, Algorithm::AlphaBeta, false, 1024 * 1024).with_threads(3);
+
+        let best = tree.get_best_move_timed(&mut board, Duration::from_secs(5));
+
+        assert_eq!(best.column, 3);
+        assert_eq!(tree.last_report().unwrap().eval, GameEvaluation::Win(1));
+    }
+
+    #[test]
+    fn test_mcts() {
+        let mut board = <Board>::from_columns(&[0, 0, 1, 1, 2, 2]);
+
+        let tree: Tree<Board, BoardMove, BoardError> =
+            Tree::new(1, Algorithm::Mcts, false, 0).with_iterations(2_000);
+        assert_eq!(tree.get_best_move(&mut board).column, 3);
+
+        let report = tree.last_report().unwrap();
+        assert_eq!(report.alg, Algorithm::Mcts);
+        assert_eq!(report.eval, GameEvaluation::Win(1));
+        assert_eq!(report.depth, report.pv.len());
+        assert!(report.nodes > 0);
+
+        let lines = tree.analyze(&mut board);
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0].0.column, 3);
+        assert_eq!(lines[0].2[0].column, 3);
+
+        let mut timed: Tree<Board, BoardMove, BoardError> =
+            Tree::new(1, Algorithm::Mcts, false, 0).with_threads(4);
+        let best = timed.get_best_move_timed(&mut board, Duration::from_millis(100));
+        assert_eq!(best.column, 3);
+    }
+
+    #[rstest]
+    #[case(&[], 0)]
+    #[case(&[], 1024 * 1024)]
+    #[case(&[3, 3, 2, 4], 0)]
+    #[case(&[3, 3, 2, 4, 4, 2], 1024 * 1024)]
+    fn test_move_ordering(#[case] moves: &[usize], #[case] tt_size: usize) {
+        let mut board = <Board>::from_columns(moves);
+
+        let plain: Tree<Board, BoardMove, BoardError> =
+            Tree::new(8, Algorithm::AlphaBeta, false, tt_size).with_move_ordering(false);
+        let (plain_eval, _) = plain.search(&mut board, &[]);
+
+        let ordered: Tree<Board, BoardMove, BoardError> =
+            Tree::new(8, Algorithm::AlphaBeta, false, tt_size);
+        let (ordered_eval, _) = ordered.search(&mut board, &[]);
+
+        assert_eq!(plain_eval, ordered_eval);
+        assert!(ordered.eval_call_count() < plain.eval_call_count() / 2);
+    }
+
+    #[rstest]
+    #[case(&[0, 0, 1, 1, 2, 2], Algorithm::AlphaBeta, 3, GameEvaluation::Win(1))]
+    #[case(&[0, 0, 1, 1, 2, 2], Algorithm::MiniMax, 3, GameEvaluation::Win(1))]
+    #[case(&[1, 6, 2, 6], Algorithm::AlphaBeta, 3, GameEvaluation::Win(3))]
+    #[case(&[6, 1, 6, 2, 5], Algorithm::AlphaBeta, 3, GameEvaluation::Lose(3))]
+    #[case(&[1, 5, 3, 3, 5, 5, 1, 3, 1], Algorithm::AlphaBeta, 1, GameEvaluation::Win(4))]
+    #[case(&[1, 5, 3, 3, 5, 5, 1, 3, 1], Algorithm::MiniMax, 1, GameEvaluation::Win(4))]
+    fn test_quickest_win(
+        #[case] moves: &[usize],
+        #[case] alg: Algorithm,
+        #[case] expected_column: usize,
+        #[case] expected_eval: GameEvaluation,
+    ) {
+        let mut board = <Board>::from_columns(moves);
+
+        for tt_size in [0, 1024 * 1024] {
+            let tree: Tree<Board, BoardMove, BoardError> = Tree::new(6, alg, false, tt_size);
+
+            assert_eq!(tree.get_best_move(&mut board).column, expected_column);
+            assert_eq!(tree.last_report().unwrap().eval, expected_eval);
+        }
+    }
+
+    #[test]
+    fn test_deepening_after_table_fills() {
+        let mut board = <Board>::from_columns(&[]);
+        let mut tree: Tree<Board, BoardMove, BoardError> =
+            Tree::new(1, Algorithm::AlphaBeta, false, 16 * 1024 * 1024);
+
+        tree.get_best_move_deepening(&mut board, 8, Some(Duration::from_secs(60)), |_| {});
+        let pv = tree.last_report().unwrap().pv;
+        board.apply_move(&pv[0]).unwrap();
+        board.apply_move(&pv[1]).unwrap();
+
+        // The table already has most of this search, which mustn't pass for
+        // the end of every game
+        tree.get_best_move_deepening(&mut board, 8, Some(Duration::from_secs(60)), |_| {});
+        let report = tree.last_report().unwrap();
+        assert!(!report.eval.is_terminal());
+        assert_eq!(report.depth, 8);
+    }
+
+    #[rstest]
+    #[case(&[])]
+    #[case(&[3, 3, 4, 5])]
+    #[case(&[2, 2, 3, 4])]
+    fn test_full_pv(#[case] moves: &[usize]) {
+        let mut board = <Board>::from_columns(moves);
+
+        let mut tree: Tree<Board, BoardMove, BoardError> =
+            Tree::new(1, Algorithm::AlphaBeta, false, 1024 * 1024);
+        let best = tree.get_best_move_timed(&mut board, Duration::from_millis(200));
+
+        let report = tree.last_report().unwrap();
+        assert_eq!(report.pv[0], best);
+
+        for m in &report.pv {
+            board.apply_move(m).unwrap();
+        }
+        assert!(report.pv.len() + 1 == report.depth || board.is_over());
+    }
+
+    #[test]
+    fn test_eval_after_move() {
+        // A bot that sees its move wins next turn has the win a ply away
+        let mut board = <Board>::from_columns(&[0, 6, 0, 6, 0, 6]);
+        let tree: Tree<Board, BoardMove, BoardError> = Tree::new(4, Algorithm::AlphaBeta, false, 0);
+        let best = tree.get_best_move(&mut board);
 
-        self.fmt_rec(f, &mut board)
+        let report = tree.last_report().unwrap();
+        assert_eq!(report.eval, GameEvaluation::Win(1));
+        board.apply_move(&best).unwrap();
+        assert_eq!(report.eval_after_move(), board.evaluate(false));
     }
 }
@@ -0,0 +1,509 @@
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    io::{self, Write},
+    rc::Rc,
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{
+        self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+    },
+};
+
+use crate::{
+    board::{
+        board::{Board, BoardError},
+        board_move::BoardMove,
+        piece::Piece,
+    },
+    game::{
+        Ending, Evaluate, GameEvaluation, GameResult, MovePiece, MoveRecord, Play, PlayerAction,
+    },
+    observer::{format_pv, GameObserver},
+    player::config::PlayerConfig,
+    tree::{Algorithm, Tree},
+};
+
+/// How far an ongoing evaluation has to lean to fill the eval bar.
+const EVAL_BAR_SCALE: isize = 50;
+
+const EVAL_BAR_WIDTH: usize = 20;
+
+/// Gaps between the board and the side panel.
+const PANEL_GAP: usize = 4;
+
+/// Everything on the screen, shared by the observer drawing the game and the
+/// players reading the keyboard.
+#[derive(Debug, Clone)]
+pub struct Screen<const W: usize = 7, const H: usize = 6, const N: usize = 4> {
+    pub board: Board<W, H, N>,
+    /// The column a piece would drop into
+    pub cursor: usize,
+    /// The last evaluation, from the last search if there was one
+    pub eval: GameEvaluation,
+    /// Whose turn it is, or how the game ended
+    pub status: String,
+    /// Anything else worth saying, e.g. a hint
+    pub message: String,
+}
+
+pub type SharedScreen<const W: usize = 7, const H: usize = 6, const N: usize = 4> =
+    Rc<RefCell<Screen<W, H, N>>>;
+
+impl<const W: usize, const H: usize, const N: usize> Screen<W, H, N> {
+    pub fn new(board: Board<W, H, N>) -> Self {
+        let eval = board.evaluate(false);
+
+        Self {
+            board,
+            cursor: W / 2,
+            eval,
+            status: String::new(),
+            message: String::new(),
+        }
+    }
+
+    pub fn shared(board: Board<W, H, N>) -> SharedScreen<W, H, N> {
+        Rc::new(RefCell::new(Self::new(board)))
+    }
+
+    /// Move the cursor `by` columns, stopping at the edges.
+    pub fn move_cursor(&mut self, by: isize) {
+        self.cursor = self.cursor.saturating_add_signed(by).min(W - 1);
+    }
+
+    /// The line above the board pointing at the cursor's column.
+    pub fn cursor_line(&self) -> String {
+        format!("{}v", " ".repeat(2 + 2 * self.cursor))
+    }
+
+    /// The move list and the eval bar, a line at a time.
+    pub fn panel(&self) -> Vec<String> {
+        let mut lines = vec!["Moves".to_string()];
+
+        // A round a line, as many of the last ones as fit beside the board
+        let history = self.board.history();
+        let rounds: Vec<_> = history.chunks(2).enumerate().collect();
+        let shown = rounds.len().saturating_sub(H - 2);
+        for (i, round) in &rounds[shown..] {
+            let columns: Vec<_> = round.iter().map(|c| (c + 1).to_string()).collect();
+            lines.push(format!("{:>3}. {}", i + 1, columns.join(" ")));
+        }
+        lines.resize(H, String::new());
+
+        lines.push(format!("Eval {}", self.eval));
+        lines.push(self.eval_bar());
+
+        lines
+    }
+
+    /// How far the game leans towards yellow, filling the bar from the left,
+    /// or red.
+    pub fn eval_bar(&self) -> String {
+        let filled = match self.eval {
+            GameEvaluation::Win(_) | GameEvaluation::PlusInfinity => EVAL_BAR_WIDTH,
+            GameEvaluation::Lose(_) | GameEvaluation::MinusInfinity => 0,
+            GameEvaluation::Draw => EVAL_BAR_WIDTH / 2,
+            GameEvaluation::OnGoing(eval) => {
+                let eval = eval.clamp(-EVAL_BAR_SCALE, EVAL_BAR_SCALE) + EVAL_BAR_SCALE;
+                eval as usize * EVAL_BAR_WIDTH / (2 * EVAL_BAR_SCALE as usize)
+            }
+        };
+
+        format!(
+            "[{}{}]",
+            "Y".repeat(filled),
+            "R".repeat(EVAL_BAR_WIDTH - filled)
+        )
+    }
+
+    /// Draw the whole screen, the board as the game prints it with the cursor
+    /// above it, the side panel to its right and the status below.
+    pub fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+
+        let mut board = self.board.clone();
+        board.calculate_threats();
+        let board = board.to_string();
+
+        let mut row = 0;
+        queue!(out, MoveTo(0, row), Print(self.cursor_line()))?;
+        for line in board.lines() {
+            row += 1;
+            queue!(out, MoveTo(0, row), Print(line))?;
+        }
+
+        let panel_column = (2 * W + 3 + PANEL_GAP) as u16;
+        for (i, line) in self.panel().iter().enumerate() {
+            queue!(out, MoveTo(panel_column, i as u16), Print(line))?;
+        }
+
+        let row = row.max(H as u16 + 2) + 1;
+        queue!(
+            out,
+            MoveTo(0, row),
+            Print(&self.status),
+            MoveTo(0, row + 1),
+            Print(&self.message)
+        )?;
+
+        out.flush()
+    }
+}
+
+/// Draw `screen` on stdout, for when something changed. Nothing can be done
+/// about a terminal that can't be drawn on, so that's ignored.
+fn redraw<const W: usize, const H: usize, const N: usize>(screen: &Screen<W, H, N>) {
+    let _ = screen.draw(&mut io::stdout());
+}
+
+/// Switches the terminal to a full screen of its own with the keys coming
+/// straight through, and back when dropped.
+#[derive(Debug)]
+pub struct Terminal;
+
+impl Terminal {
+    pub fn enter() -> io::Result<Self> {
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+
+        Ok(Self)
+    }
+
+    /// Wait for any key.
+    pub fn wait_for_key(&self) -> io::Result<()> {
+        loop {
+            if let Event::Key(KeyEvent {
+                kind: KeyEventKind::Press,
+                ..
+            }) = event::read()?
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+        let _ = disable_raw_mode();
+    }
+}
+
+/// Keeps the screen up to date with the game.
+#[derive(Debug)]
+pub struct TuiObserver<const W: usize = 7, const H: usize = 6, const N: usize = 4> {
+    screen: SharedScreen<W, H, N>,
+}
+
+impl<const W: usize, const H: usize, const N: usize> TuiObserver<W, H, N> {
+    pub fn new(screen: SharedScreen<W, H, N>) -> Self {
+        Self { screen }
+    }
+}
+
+impl<const W: usize, const H: usize, const N: usize> GameObserver<W, H, N>
+    for TuiObserver<W, H, N>
+{
+    fn on_move_requested(&mut self, board: &Board<W, H, N>, player: &dyn Play<W, H, N>) {
+        let mut screen = self.screen.borrow_mut();
+        screen.board = board.clone();
+        screen.status = match player.needs_to_see_board() {
+            true => format!(
+                "{:?} to play: left and right choose a column, enter drops, u undo, h hint, q quit",
+                board.whos_to_play()
+            ),
+            false => format!("{:?} is thinking...", board.whos_to_play()),
+        };
+        redraw(&screen);
+    }
+
+    fn on_move_played(
+        &mut self,
+        board: &Board<W, H, N>,
+        _player: &dyn Play<W, H, N>,
+        record: &MoveRecord,
+    ) {
+        let mut screen = self.screen.borrow_mut();
+        screen.board = board.clone();
+        screen.eval = match &record.search {
+            Some(report) => report.eval_after_move(),
+            None => record.eval,
+        };
+        screen.message = match &record.search {
+            Some(report) => format!("Played {}, pv: {}", record.move_data, format_pv(&report.pv)),
+            None => format!("Played {}", record.move_data),
+        };
+        redraw(&screen);
+    }
+
+    fn on_undo(&mut self, board: &Board<W, H, N>, undone: &Result<(), BoardError>) {
+        let mut screen = self.screen.borrow_mut();
+        screen.board = board.clone();
+        screen.eval = board.evaluate(false);
+        screen.message = match undone {
+            Ok(()) => "Took back the last round".to_string(),
+            Err(e) => e.to_string(),
+        };
+        redraw(&screen);
+    }
+
+    fn on_illegal_move(
+        &mut self,
+        _board: &Board<W, H, N>,
+        player: &dyn Play<W, H, N>,
+        move_data: &BoardMove,
+        error: &BoardError,
+    ) {
+        self.screen.borrow_mut().message = format!(
+            "{} played {} which isn't allowed: {}",
+            player, move_data, error
+        );
+    }
+
+    fn on_forfeit(&mut self, _board: &Board<W, H, N>, player: &dyn Play<W, H, N>, reason: &str) {
+        self.screen.borrow_mut().message = format!("{} forfeits: {}", player, reason);
+    }
+
+    fn on_game_over(
+        &mut self,
+        result: &GameResult<W, H, N>,
+        _player1: &dyn Play<W, H, N>,
+        _player2: &dyn Play<W, H, N>,
+    ) {
+        let mut screen = self.screen.borrow_mut();
+        screen.board = result.board.clone();
+        screen.status = match (result.winner, result.ending) {
+            (Some(winner), Ending::Connect) => format!("{:?} wins! Press any key", winner),
+            (Some(winner), _) => format!("{:?} wins by forfeit. Press any key", winner),
+            (None, _) => "It's a draw! Press any key".to_string(),
+        };
+        redraw(&screen);
+    }
+}
+
+/// A person playing with the keyboard on the full screen.
+#[derive(Debug)]
+pub struct TuiHuman<const W: usize = 7, const H: usize = 6, const N: usize = 4> {
+    pub color: Piece,
+    screen: SharedScreen<W, H, N>,
+    /// Searches for hints
+    hints: Tree<Board<W, H, N>, BoardMove, BoardError>,
+}
+
+impl<const W: usize, const H: usize, const N: usize> TuiHuman<W, H, N> {
+    /// `depth`, `alg` and `tt_size` are for the search behind hints and work
+    /// the same as a bot's.
+    pub fn new(
+        color: Piece,
+        screen: SharedScreen<W, H, N>,
+        depth: usize,
+        alg: Algorithm,
+        tt_size: usize,
+    ) -> Self {
+        Self {
+            color,
+            screen,
+            hints: Tree::new(depth, alg, false, tt_size),
+        }
+    }
+
+    /// Put the cursor on the best move and say why.
+    fn hint(&self, mut board: Board<W, H, N>) {
+        self.say("Thinking...");
+
+        let lines = self.hints.analyze(&mut board);
+        let mut screen = self.screen.borrow_mut();
+        if let Some((best, eval, pv)) = lines.first() {
+            screen.cursor = best.column;
+            screen.message = format!("Hint: play {} ({}), pv: {}", best, eval, format_pv(pv));
+        }
+        redraw(&screen);
+    }
+
+    fn say(&self, message: &str) {
+        let mut screen = self.screen.borrow_mut();
+        screen.message = message.to_string();
+        redraw(&screen);
+    }
+
+    fn read_action(&mut self, board: Board<W, H, N>) -> io::Result<PlayerAction> {
+        // Keys pressed while the other player was thinking aren't for this move
+        while event::poll(Duration::ZERO)? {
+            event::read()?;
+        }
+
+        loop {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Left => self.screen.borrow_mut().move_cursor(-1),
+                KeyCode::Right => self.screen.borrow_mut().move_cursor(1),
+                KeyCode::Char(c) if c.is_ascii_digit() => {
+                    let column = c.to_digit(10).unwrap() as usize;
+                    if (1..=W).contains(&column) {
+                        self.screen.borrow_mut().cursor = column - 1;
+                    }
+                }
+                KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Down => {
+                    let move_data = BoardMove::from(self.screen.borrow().cursor);
+                    if board.is_move_valid(&move_data) {
+                        self.say("");
+                        return Ok(PlayerAction::Move(move_data));
+                    }
+                    self.say("That column is full");
+                    continue;
+                }
+                KeyCode::Char('u') => return Ok(PlayerAction::Undo),
+                KeyCode::Char('h') => {
+                    self.hint(board.clone());
+                    continue;
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(PlayerAction::Forfeit("Quit".to_string()))
+                }
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    return Ok(PlayerAction::Forfeit("Quit".to_string()))
+                }
+                _ => continue,
+            }
+
+            redraw(&self.screen.borrow());
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const N: usize> Display for TuiHuman<W, H, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.color)
+    }
+}
+
+impl<const W: usize, const H: usize, const N: usize> Play<W, H, N> for TuiHuman<W, H, N> {
+    /// Quitting plays off the board, which the game takes as an illegal move.
+    fn get_move(&mut self, board: Board<W, H, N>) -> BoardMove {
+        loop {
+            match self.get_action(board.clone()) {
+                PlayerAction::Move(move_data) => return move_data,
+                PlayerAction::Undo => self.say("You can't undo right now"),
+                PlayerAction::Forfeit(_) => return W.into(),
+            }
+        }
+    }
+
+    fn get_action(&mut self, board: Board<W, H, N>) -> PlayerAction {
+        self.read_action(board)
+            .unwrap_or_else(|e| PlayerAction::Forfeit(format!("Couldn't read the keyboard: {}", e)))
+    }
+
+    fn needs_to_see_board(&self) -> bool {
+        true
+    }
+
+    fn should_announce_move(&self) -> bool {
+        false
+    }
+}
+
+/// Build the player `config` describes, with humans playing on `screen`.
+pub fn build_player<const W: usize, const H: usize, const N: usize>(
+    config: &PlayerConfig,
+    color: Piece,
+    screen: &SharedScreen<W, H, N>,
+) -> anyhow::Result<Box<dyn Play<W, H, N>>> {
+    if config.kind != "human" {
+        return config.build(color);
+    }
+
+    Ok(Box::new(TuiHuman::new(
+        color,
+        screen.clone(),
+        config.depth,
+        config.alg.parse()?,
+        config.tt_size * 1024 * 1024,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn screen(moves: &str) -> Screen {
+        Screen::new(Board::from_moves(moves).unwrap())
+    }
+
+    #[rstest]
+    #[case(0, -1, 0)]
+    #[case(3, -1, 2)]
+    #[case(3, 1, 4)]
+    #[case(6, 1, 6)]
+    fn test_move_cursor(#[case] from: usize, #[case] by: isize, #[case] to: usize) {
+        let mut screen = screen("");
+        screen.cursor = from;
+
+        screen.move_cursor(by);
+
+        assert_eq!(screen.cursor, to);
+        // Over the column's square in the board's picture
+        assert_eq!(screen.cursor_line().len(), 3 + 2 * to);
+    }
+
+    #[rstest]
+    #[case(GameEvaluation::OnGoing(0), 10)]
+    #[case(GameEvaluation::OnGoing(25), 15)]
+    #[case(GameEvaluation::OnGoing(-400), 0)]
+    #[case(GameEvaluation::Win(3), 20)]
+    #[case(GameEvaluation::Lose(0), 0)]
+    #[case(GameEvaluation::Draw, 10)]
+    fn test_eval_bar(#[case] eval: GameEvaluation, #[case] yellow: usize) {
+        let mut screen = screen("");
+        screen.eval = eval;
+
+        let bar = screen.eval_bar();
+
+        assert_eq!(bar.len(), EVAL_BAR_WIDTH + 2);
+        assert_eq!(bar.matches('Y').count(), yellow);
+    }
+
+    #[test]
+    fn test_panel() {
+        let panel = screen("44536").panel();
+
+        assert_eq!(
+            panel,
+            vec![
+                "Moves",
+                "  1. 4 4",
+                "  2. 5 3",
+                "  3. 6",
+                "",
+                "",
+                "Eval +5",
+                &screen("44536").eval_bar()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_panel_long_game() {
+        let panel = screen("1212343434565665").panel();
+
+        // Only the last rounds fit beside the board
+        assert_eq!(panel.len(), 8);
+        assert_eq!(panel[1], "  5. 3 4");
+        assert_eq!(panel[4], "  8. 6 5");
+    }
+}
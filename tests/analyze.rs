@@ -0,0 +1,22 @@
+//! The output of `quatre_con analyze`, which should read back as moves.
+
+use std::process::Command;
+
+#[test]
+fn test_moves_in_the_input_notation() {
+    let output = Command::new(env!("CARGO_BIN_EXE_quatre_con"))
+        .args(["analyze", "121212", "--depth", "3"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let best = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix(" 1. "))
+        .unwrap_or_else(|| panic!("No best move in {}", stdout));
+
+    // Yellow wins by playing the leftmost column
+    assert!(best.starts_with("1 "), "{}", best);
+    assert!(best.ends_with("pv: 1"), "{}", best);
+}
@@ -0,0 +1,110 @@
+//! Network play on localhost, between two processes or against a host that misbehaves.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    process::{Child, Command, Stdio},
+    thread,
+};
+
+fn quatre_con(args: &[&str]) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_quatre_con"))
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap()
+}
+
+/// The moves of the finished game from what a process printed.
+fn moves(output: &str) -> &str {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("Moves: "))
+        .unwrap_or_else(|| panic!("No moves in {}", output))
+}
+
+#[test]
+fn test_host_and_join() {
+    let mut host = quatre_con(&[
+        "--position",
+        "44",
+        "host",
+        "--port",
+        "0",
+        "--color",
+        "red",
+        "--player",
+        "bot:depth=3",
+    ]);
+
+    // The host says which port it got before it waits
+    let mut host_output = BufReader::new(host.stdout.take().unwrap());
+    let mut line = String::new();
+    host_output.read_line(&mut line).unwrap();
+    let port = line
+        .trim()
+        .strip_prefix("Waiting for another player on port ")
+        .unwrap_or_else(|| panic!("Unexpected {}", line));
+
+    let joiner = quatre_con(&[
+        "join",
+        &format!("127.0.0.1:{}", port),
+        "--player",
+        "bot:depth=2",
+    ]);
+
+    let joiner = joiner.wait_with_output().unwrap();
+    let mut host_rest = String::new();
+    host_output.read_to_string(&mut host_rest).unwrap();
+    assert!(host.wait().unwrap().success());
+    assert!(joiner.status.success());
+
+    let joiner_output = String::from_utf8(joiner.stdout).unwrap();
+    assert!(joiner_output.contains("Connected, you play Yellow"));
+    assert!(host_rest.contains("Connected, you play Red"));
+    assert!(!host_rest.contains("forfeits"), "{}", host_rest);
+    assert!(!joiner_output.contains("forfeits"), "{}", joiner_output);
+
+    let played = moves(&host_rest);
+    assert!(played.starts_with("44"));
+    assert_eq!(played, moves(&joiner_output));
+}
+
+#[test]
+fn test_host_gone() {
+    let joiner = quatre_con(&["join", "127.0.0.1:1"])
+        .wait_with_output()
+        .unwrap();
+
+    assert!(!joiner.status.success());
+    assert!(String::from_utf8(joiner.stderr)
+        .unwrap()
+        .contains("Couldn't reach the host"));
+}
+
+#[test]
+fn test_host_with_unsupported_size() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let host = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut hello = String::new();
+        reader.read_line(&mut hello).unwrap();
+        writeln!(&stream, "start red 3 3 3").unwrap();
+        let mut ready = String::new();
+        let _ = reader.read_line(&mut ready);
+    });
+
+    let joiner = quatre_con(&["join", &address]).wait_with_output().unwrap();
+    host.join().unwrap();
+
+    // An error the joiner reports, not a crash
+    assert_eq!(joiner.status.code(), Some(1));
+    assert!(String::from_utf8(joiner.stderr)
+        .unwrap()
+        .contains("Unsupported board size 3x3 connect 3"));
+}
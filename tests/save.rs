@@ -0,0 +1,32 @@
+//! Resuming saved games with `quatre_con --load`.
+
+use std::{fs, process::Command};
+
+use quatre_con::{
+    game::Ending,
+    player::config::PlayerConfig,
+    record::{Finish, GameRecord},
+};
+
+#[test]
+fn test_forfeited_game_is_not_resumed() {
+    let path = std::env::temp_dir().join(format!("quatre_con_forfeit_{}.json", std::process::id()));
+
+    // Red gave up before anyone connected
+    let mut record = GameRecord::new(7, 6, 4, PlayerConfig::default(), PlayerConfig::default());
+    record.moves = "4453".to_string();
+    record.result = Some(Finish::Red);
+    record.ending = Some(Ending::Forfeit);
+    record.save(&path).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_quatre_con"))
+        .arg("--load")
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("already over"), "{}", stderr);
+}
@@ -0,0 +1,186 @@
+//! The web API of `quatre_con serve`, over HTTP on localhost.
+
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+};
+
+use quatre_con::{
+    record::Finish,
+    server::{BotMove, Line, State},
+};
+use serde::de::DeserializeOwned;
+
+/// A running server, stopped when dropped.
+struct Server {
+    child: Child,
+    url: String,
+}
+
+impl Server {
+    fn start(args: &[&str]) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_quatre_con"))
+            .args(args)
+            .args(["serve", "--port", "0", "--bot", "bot:depth=3"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        // It says where it's serving once it's listening
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let url = line
+            .trim()
+            .strip_prefix("Serving on ")
+            .unwrap_or_else(|| panic!("Unexpected {}", line))
+            .to_string();
+
+        Self { child, url }
+    }
+
+    fn get(&self, path: &str) -> (u16, String) {
+        send(ureq::get(&format!("{}{}", self.url, path)).call())
+    }
+
+    fn post(&self, path: &str, body: &str) -> (u16, String) {
+        send(
+            ureq::post(&format!("{}{}", self.url, path))
+                .set("Content-Type", "application/json")
+                .send_string(body),
+        )
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The status and body of the reply to a request.
+fn send(result: Result<ureq::Response, ureq::Error>) -> (u16, String) {
+    match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => {
+            (response.status(), response.into_string().unwrap())
+        }
+        Err(e) => panic!("Couldn't reach the server: {}", e),
+    }
+}
+
+/// The JSON body of a request that worked.
+fn json<T: DeserializeOwned>((status, body): (u16, String)) -> T {
+    assert_eq!(status, 200, "{}", body);
+    serde_json::from_str(&body).unwrap()
+}
+
+/// The status and error of a request that failed.
+fn error((status, body): (u16, String)) -> (u16, String) {
+    assert_ne!(status, 200, "{}", body);
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    (status, body["error"].as_str().unwrap().to_string())
+}
+
+#[test]
+fn test_page() {
+    let server = Server::start(&[]);
+
+    let page = ureq::get(&server.url).call().unwrap();
+
+    assert_eq!(page.content_type(), "text/html");
+    assert!(page.into_string().unwrap().contains("/api/state"));
+}
+
+#[test]
+fn test_game_against_the_bot() {
+    let server = Server::start(&[]);
+
+    let state: State = json(server.get("/api/state"));
+    assert_eq!((state.width, state.height, state.connect), (7, 6, 4));
+    assert_eq!(state.moves, "");
+
+    let state: State = json(server.post("/api/move", r#"{"column": 4}"#));
+    assert_eq!(state.moves, "4");
+    assert_eq!(state.to_play, "red");
+
+    let bot_move: BotMove = json(server.post("/api/bot", ""));
+    assert_eq!(bot_move.state.moves.len(), 2);
+    assert_eq!(bot_move.state.to_play, "yellow");
+    assert_eq!(bot_move.search.unwrap().depth, 3);
+
+    let lines: Vec<Line> = json(server.get("/api/analysis?depth=3"));
+    assert_eq!(lines.len(), 7);
+
+    let state: State = json(server.post("/api/undo", ""));
+    assert_eq!(state.moves, "");
+    assert_eq!(error(server.post("/api/undo", "")).0, 400);
+}
+
+#[test]
+fn test_new_game() {
+    let server = Server::start(&["--width", "9", "--height", "7", "--position", "5"]);
+
+    let state: State = json(server.post("/api/new", r#"{"moves": "121212", "bot": "random"}"#));
+    assert_eq!(state.width, 9);
+    assert_eq!(state.rows.len(), 7);
+    assert_eq!(state.moves, "5121212");
+
+    let bot_move: BotMove = json(server.post("/api/bot", ""));
+    assert!(bot_move.search.is_none());
+
+    let (status, error) = error(server.post("/api/new", r#"{"bot": "bot:depth=x"}"#));
+    assert_eq!(status, 400);
+    assert!(error.contains("invalid digit"), "{}", error);
+}
+
+#[test]
+fn test_game_over() {
+    let server = Server::start(&["--position", "121212"]);
+
+    let state: State = json(server.post("/api/move", r#"{"column": 1}"#));
+    assert_eq!(state.result, Some(Finish::Yellow));
+    assert!(state.legal.is_empty());
+
+    assert_eq!(
+        error(server.post("/api/move", r#"{"column": 2}"#)),
+        (400, "The game is over".to_string())
+    );
+    assert_eq!(error(server.post("/api/bot", "")).0, 400);
+}
+
+#[test]
+fn test_bad_requests() {
+    let server = Server::start(&[]);
+
+    assert_eq!(error(server.post("/api/move", r#"{"col": 3}"#)).0, 400);
+    assert_eq!(error(server.post("/api/move", r#"{"column": 8}"#)).0, 400);
+    assert_eq!(error(server.post("/api/move", r#"{"column": 0}"#)).0, 400);
+    assert_eq!(error(server.get("/api/move")).0, 405);
+    assert_eq!(error(server.get("/favicon.ico")).0, 404);
+}
+
+#[test]
+fn test_requests_not_sent_as_json() {
+    let server = Server::start(&[]);
+    let post = |path: &str| ureq::post(&format!("{}{}", server.url, path));
+
+    let (status, message) = error(send(
+        post("/api/move")
+            .set("Content-Type", "text/plain")
+            .send_string(r#"{"column": 4}"#),
+    ));
+    assert_eq!(status, 415);
+    assert!(message.contains("application/json"), "{}", message);
+    assert_eq!(error(send(post("/api/bot").call())).0, 415);
+
+    // Neither was played
+    let state: State = json(server.get("/api/state"));
+    assert_eq!(state.moves, "");
+
+    let charset = post("/api/move")
+        .set("Content-Type", "application/json; charset=utf-8")
+        .send_string(r#"{"column": 4}"#);
+    assert_eq!(json::<State>(send(charset)).moves, "4");
+}